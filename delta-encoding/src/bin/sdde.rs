@@ -0,0 +1,6 @@
+use simple_direct_delta_encoding::run_cli;
+
+fn main() {
+    let code = run_cli(std::env::args().skip(1), &mut std::io::stdout().lock());
+    std::process::exit(code);
+}
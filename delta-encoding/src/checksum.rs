@@ -0,0 +1,108 @@
+use dispnet_hash::{DispnetHash, HashType};
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh3::Xxh3Default;
+
+/// Hash of the data which is used to validate the source and the target of a patch
+///
+///
+/// The id is stored in the patch header, so the receiver validates the patch with the same algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChecksumAlgorithm {
+    /// CRC of `dispnet-hash` as decimal digits (the checksum of the version 0 patches)
+    #[default]
+    DispnetCrc,
+    /// CRC-32C (Castagnoli), 4 bytes big endian
+    Crc32c,
+    /// 64 bit xxHash3, 8 bytes big endian
+    XxHash3,
+    /// BLAKE3, 32 bytes
+    Blake3,
+    /// SHA-256, 32 bytes
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    pub fn id(&self) -> u8 {
+        match self {
+            ChecksumAlgorithm::DispnetCrc => 0,
+            ChecksumAlgorithm::Crc32c => 1,
+            ChecksumAlgorithm::XxHash3 => 2,
+            ChecksumAlgorithm::Blake3 => 3,
+            ChecksumAlgorithm::Sha256 => 4,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<ChecksumAlgorithm> {
+        match id {
+            0 => Some(ChecksumAlgorithm::DispnetCrc),
+            1 => Some(ChecksumAlgorithm::Crc32c),
+            2 => Some(ChecksumAlgorithm::XxHash3),
+            3 => Some(ChecksumAlgorithm::Blake3),
+            4 => Some(ChecksumAlgorithm::Sha256),
+            _ => None,
+        }
+    }
+
+    /// Only the cryptographic hashes protect against intentionally changed patches
+    pub fn is_cryptographic(&self) -> bool {
+        matches!(self, ChecksumAlgorithm::Blake3 | ChecksumAlgorithm::Sha256)
+    }
+
+    pub fn checksum(&self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            ChecksumAlgorithm::DispnetCrc => DispnetHash::create(HashType::CRC, bytes, None).digest_value,
+            ChecksumAlgorithm::Crc32c => crc32c::crc32c(bytes).to_be_bytes().to_vec(),
+            ChecksumAlgorithm::XxHash3 => xxhash_rust::xxh3::xxh3_64(bytes).to_be_bytes().to_vec(),
+            ChecksumAlgorithm::Blake3 => blake3::hash(bytes).as_bytes().to_vec(),
+            ChecksumAlgorithm::Sha256 => Sha256::digest(bytes).to_vec(),
+        }
+    }
+
+    /// Hasher which creates the same checksum from the bytes passed in parts
+    pub(crate) fn hasher(&self) -> ChecksumHasher {
+        match self {
+            ChecksumAlgorithm::DispnetCrc => ChecksumHasher::DispnetCrc(Vec::new()),
+            ChecksumAlgorithm::Crc32c => ChecksumHasher::Crc32c(0),
+            ChecksumAlgorithm::XxHash3 => ChecksumHasher::XxHash3(Box::default()),
+            ChecksumAlgorithm::Blake3 => ChecksumHasher::Blake3(Box::default()),
+            ChecksumAlgorithm::Sha256 => ChecksumHasher::Sha256(Sha256::new()),
+        }
+    }
+}
+
+/// Incremental checksum, so large data (e.g. the state) is hashed without a copy of all bytes
+///
+///
+/// `dispnet-hash` has no incremental API, the bytes of [`ChecksumAlgorithm::DispnetCrc`] are collected and hashed at the end.
+pub(crate) enum ChecksumHasher {
+    DispnetCrc(Vec<u8>),
+    Crc32c(u32),
+    XxHash3(Box<Xxh3Default>),
+    Blake3(Box<blake3::Hasher>),
+    Sha256(Sha256),
+}
+
+impl ChecksumHasher {
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        match self {
+            ChecksumHasher::DispnetCrc(collected) => collected.extend(bytes),
+            ChecksumHasher::Crc32c(crc) => *crc = crc32c::crc32c_append(*crc, bytes),
+            ChecksumHasher::XxHash3(hasher) => hasher.update(bytes),
+            ChecksumHasher::Blake3(hasher) => {
+                hasher.update(bytes);
+            },
+            ChecksumHasher::Sha256(hasher) => hasher.update(bytes),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        match self {
+            ChecksumHasher::DispnetCrc(collected) => ChecksumAlgorithm::DispnetCrc.checksum(&collected),
+            ChecksumHasher::Crc32c(crc) => crc.to_be_bytes().to_vec(),
+            ChecksumHasher::XxHash3(hasher) => hasher.digest().to_be_bytes().to_vec(),
+            ChecksumHasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+            ChecksumHasher::Sha256(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
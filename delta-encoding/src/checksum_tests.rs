@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod checksum {
+    use crate::*;
+
+    const ALGORITHMS: [ChecksumAlgorithm; 5] = [
+        ChecksumAlgorithm::DispnetCrc,
+        ChecksumAlgorithm::Crc32c,
+        ChecksumAlgorithm::XxHash3,
+        ChecksumAlgorithm::Blake3,
+        ChecksumAlgorithm::Sha256,
+    ];
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn checksum_in_parts() {
+        let bytes = "abcdefghijklmnopqrstuvwxyz".repeat(1000).into_bytes();
+        for algorithm in ALGORITHMS {
+            let mut hasher = algorithm.hasher();
+            for part in bytes.chunks(7) {
+                hasher.update(part);
+            }
+            assert_eq!(hasher.finish(), algorithm.checksum(&bytes), "{:?}", algorithm);
+        }
+    }
+
+    #[test]
+    fn checksum_values() {
+        assert_eq!(hex(&ChecksumAlgorithm::Crc32c.checksum(b"123456789")), "e3069283");
+        assert_eq!(hex(&ChecksumAlgorithm::XxHash3.checksum(b"")), "2d06800538d394c2");
+        assert_eq!(
+            hex(&ChecksumAlgorithm::Blake3.checksum(b"")),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(
+            hex(&ChecksumAlgorithm::Sha256.checksum(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn algorithm_ids() {
+        for algorithm in ALGORITHMS {
+            assert_eq!(ChecksumAlgorithm::from_id(algorithm.id()), Some(algorithm));
+        }
+        assert_eq!(ChecksumAlgorithm::from_id(200), None);
+        assert!(ChecksumAlgorithm::Sha256.is_cryptographic() && !ChecksumAlgorithm::Crc32c.is_cryptographic());
+    }
+
+    #[test]
+    fn apply_patch_with_checksum_algorithm() {
+        let props = vec![IndexedData::new(0, "Test".as_bytes().to_vec())];
+        let new_data = vec![IndexedData::new(0, "Test2".as_bytes().to_vec())];
+        for algorithm in ALGORITHMS {
+            let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+            sdd.set_checksum_algorithm(algorithm);
+            let diff_data = sdd.patch(&new_data);
+            let (header, _) = PatchHeader::from_bytes(&diff_data).unwrap();
+            assert_eq!(header.checksum_algorithm, algorithm.id());
+            assert_eq!(header.crc, algorithm.checksum(&SimpleDirectDeltaEncoding::new(&props).get_state()));
+
+            // the receiver uses the default algorithm
+            let mut sdd2 = SimpleDirectDeltaEncoding::new(&props);
+            assert!(sdd2.apply_patch(&diff_data).is_ok());
+            assert_eq!(sdd2.data_collection.get(&0).unwrap().data, "Test2".as_bytes());
+        }
+    }
+
+    #[test]
+    fn apply_patch_unknown_checksum_algorithm() {
+        let props = vec![IndexedData::new(0, "Test".as_bytes().to_vec())];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        let mut diff_data = sdd.patch(&[IndexedData::new(0, "Test2".as_bytes().to_vec())]);
+        diff_data[PATCH_MAGIC.len() + 2] = 200;
+        let mut sdd2 = SimpleDirectDeltaEncoding::new(&props);
+        let result = sdd2.apply_patch(&diff_data);
+        assert!(matches!(result, Err(SDDEError::UnknownChecksumAlgorithm { id: 200 })));
+    }
+}
@@ -0,0 +1,245 @@
+use std::io::Write;
+use std::path::Path;
+
+use crate::checksum::ChecksumAlgorithm;
+use crate::diff_algorithm::{BlockDiff, DiffAlgorithm, LcsDiff, PositionalDiff};
+use crate::inspect::PatchInspector;
+use crate::patch_format::PatchHeader;
+use crate::tree::{apply_directory_patch, DirectoryDelta, TreeChange};
+use crate::{Index, IndexedData, SDDEError, SimpleDirectDeltaEncoding};
+
+/// Exit code for failed reads or writes of a file
+pub const EXIT_IO_ERROR: i32 = 1;
+/// Exit code for unknown commands or options
+pub const EXIT_USAGE: i32 = 2;
+
+const USAGE: &str = "usage:
+  sdde diff <old> <new> [-o <patch>] [--chunk-size <bytes>] [--algorithm positional|lcs|block] [--checksum dispnet|crc32c|xxhash3|blake3|sha256]
+  sdde apply <base> <patch> [-o <out>] [--chunk-size <bytes>]
+  sdde inspect <patch>
+  sdde verify <base> <patch> [--chunk-size <bytes>]
+
+Without --chunk-size a file is a single entry, otherwise every chunk is an entry (the same chunk size is needed for diff, apply and verify).
+Without -o the patch or the patched file is written to stdout.
+
+With directories every file path is a key, the patch contains the modified, added, removed and renamed files.
+A patched directory is written to -o (a directory which does not exist yet) or replaces the base directory.";
+
+/// Exit code of the `sdde` binary for an error of the library (10 and above, [`EXIT_IO_ERROR`] for file errors)
+pub fn exit_code(error: &SDDEError) -> i32 {
+    match error {
+        SDDEError::CRC(_) => 10,
+        SDDEError::DifferenceInvalid(_) => 11,
+        SDDEError::Truncated { .. } => 12,
+        SDDEError::UnknownControlByte { .. } => 13,
+        SDDEError::LengthOverflow => 14,
+        SDDEError::RangeOutOfBounds { .. } => 15,
+        SDDEError::UnsupportedVersion { .. } => 16,
+        SDDEError::UnknownDiffAlgorithm { .. } => 17,
+        SDDEError::UnknownChecksumAlgorithm { .. } => 18,
+        SDDEError::UnknownSourceIndex { .. } => 19,
+        SDDEError::TargetMismatch { .. } => 20,
+        SDDEError::UnknownRevision { .. } => 21,
+        SDDEError::NotComposable(_) => 22,
+        SDDEError::NotReversible(_) => 23,
+        SDDEError::NotRebasable(_) => 24,
+        SDDEError::Io(_) => EXIT_IO_ERROR,
+        SDDEError::InvalidPath(_) => 25,
+    }
+}
+
+enum CliError {
+    Usage(String),
+    Io(String, std::io::Error),
+    Encoding(SDDEError),
+}
+
+impl From<SDDEError> for CliError {
+    fn from(error: SDDEError) -> Self {
+        CliError::Encoding(error)
+    }
+}
+
+#[derive(Default)]
+struct Options {
+    paths: Vec<String>,
+    output: Option<String>,
+    chunk_size: Option<usize>,
+    algorithm: Option<String>,
+    checksum: Option<String>,
+}
+
+/// Runs the `sdde` command with the arguments (without the program name), stdout is written to `out`
+///
+///
+/// Returns the exit code: 0 on success, [`EXIT_IO_ERROR`], [`EXIT_USAGE`] or the [`exit_code`] of the error.
+pub fn run_cli(args: impl IntoIterator<Item = String>, out: &mut impl Write) -> i32 {
+    match run(args.into_iter().collect(), out) {
+        Ok(()) => 0,
+        Err(CliError::Usage(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            EXIT_USAGE
+        },
+        Err(CliError::Io(path, error)) => {
+            eprintln!("{}: {}", path, error);
+            EXIT_IO_ERROR
+        },
+        Err(CliError::Encoding(error)) => {
+            eprintln!("{}", error);
+            exit_code(&error)
+        },
+    }
+}
+
+fn run(args: Vec<String>, out: &mut impl Write) -> Result<(), CliError> {
+    let Some((command, args)) = args.split_first() else {
+        return Err(CliError::Usage("Missing command".to_owned()));
+    };
+    let options = parse_options(args)?;
+    let is_dir = options.paths.first().is_some_and(|path| Path::new(path).is_dir());
+    if is_dir && options.chunk_size.is_some() {
+        return Err(CliError::Usage("Directories can not be split into chunks".to_owned()));
+    }
+    match (command.as_str(), options.paths.as_slice()) {
+        ("diff", [old, new]) if is_dir => {
+            let mut delta = DirectoryDelta::new(Path::new(old))?;
+            delta.set_diff_algorithm(diff_algorithm(options.algorithm.as_deref().unwrap_or("positional"))?);
+            if options.checksum.is_some() {
+                return Err(CliError::Usage("Directories use the default checksum algorithm".to_owned()));
+            }
+            let patch = delta.patch(Path::new(new))?;
+            write_output(&options, &patch.patch, out)
+        },
+        ("apply", [base, patch]) if is_dir => {
+            let target = options.output.as_deref().unwrap_or(base);
+            let changes = apply_directory_patch(Path::new(base), &read_file(patch)?, Path::new(target))?;
+            write_changes(&changes, out)
+        },
+        ("verify", [base, patch]) if is_dir => {
+            let changes = DirectoryDelta::new(Path::new(base))?.apply_patch(&read_file(patch)?)?;
+            writeln!(out, "ok: the patch applies to the base directory")
+                .map_err(|error| CliError::Io("stdout".to_owned(), error))?;
+            write_changes(&changes, out)
+        },
+        ("diff", [old, new]) => {
+            let mut encoding = SimpleDirectDeltaEncoding::new(&read_entries(old, &options)?);
+            encoding.set_diff_algorithm(diff_algorithm(options.algorithm.as_deref().unwrap_or("positional"))?);
+            if let Some(name) = options.checksum.as_deref() {
+                encoding.set_checksum_algorithm(checksum_algorithm(name)?);
+            }
+            let patch = encoding.patch(&read_entries(new, &options)?);
+            write_output(&options, &patch, out)
+        },
+        ("apply", [base, patch]) => {
+            let mut encoding = SimpleDirectDeltaEncoding::new(&read_entries(base, &options)?);
+            encoding.apply_patch(&read_file(patch)?)?;
+            let data: Vec<IndexedData> = encoding.data_collection.into_values().collect();
+            write_output(&options, &SimpleDirectDeltaEncoding::fold_index(&data), out)
+        },
+        ("inspect", [patch]) => {
+            let inspector = PatchInspector::new(&read_file(patch)?)?;
+            write!(out, "{}", inspector).map_err(|error| CliError::Io("stdout".to_owned(), error))
+        },
+        ("verify", [base, patch]) => {
+            let patch = read_file(patch)?;
+            let (header, _) = PatchHeader::from_bytes(&patch)?;
+            // a failed patch is rolled back, so the error is the result of the verification
+            SimpleDirectDeltaEncoding::new(&read_entries(base, &options)?).apply_patch(&patch)?;
+            let target = if header.target_crc.is_empty() {
+                "the patch has no target CRC"
+            } else {
+                "target CRC verified"
+            };
+            writeln!(out, "ok: the patch applies to the base, {}", target)
+                .map_err(|error| CliError::Io("stdout".to_owned(), error))
+        },
+        ("diff" | "apply" | "inspect" | "verify", _) => Err(CliError::Usage(format!("Wrong arguments for {}", command))),
+        _ => Err(CliError::Usage(format!("Unknown command {}", command))),
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, CliError> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| CliError::Usage(format!("Missing value of {}", arg)))
+        };
+        match arg.as_str() {
+            "-o" | "--output" => options.output = Some(value()?),
+            "--chunk-size" => {
+                let chunk_size = value()?;
+                match chunk_size.parse::<usize>() {
+                    Ok(chunk_size) if chunk_size > 0 => options.chunk_size = Some(chunk_size),
+                    _ => return Err(CliError::Usage(format!("Invalid chunk size {}", chunk_size))),
+                }
+            },
+            "--algorithm" => options.algorithm = Some(value()?),
+            "--checksum" => options.checksum = Some(value()?),
+            _ if arg.starts_with('-') && arg.len() > 1 => return Err(CliError::Usage(format!("Unknown option {}", arg))),
+            _ => options.paths.push(arg.clone()),
+        }
+    }
+    Ok(options)
+}
+
+fn diff_algorithm(name: &str) -> Result<Box<dyn DiffAlgorithm>, CliError> {
+    match name {
+        "positional" => Ok(Box::new(PositionalDiff::default())),
+        "lcs" => Ok(Box::new(LcsDiff::default())),
+        "block" => Ok(Box::new(BlockDiff::default())),
+        _ => Err(CliError::Usage(format!("Unknown algorithm {}", name))),
+    }
+}
+
+fn checksum_algorithm(name: &str) -> Result<ChecksumAlgorithm, CliError> {
+    match name {
+        "dispnet" => Ok(ChecksumAlgorithm::DispnetCrc),
+        "crc32c" => Ok(ChecksumAlgorithm::Crc32c),
+        "xxhash3" => Ok(ChecksumAlgorithm::XxHash3),
+        "blake3" => Ok(ChecksumAlgorithm::Blake3),
+        "sha256" => Ok(ChecksumAlgorithm::Sha256),
+        _ => Err(CliError::Usage(format!("Unknown checksum algorithm {}", name))),
+    }
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, CliError> {
+    std::fs::read(path).map_err(|error| CliError::Io(path.to_owned(), error))
+}
+
+/// The file as a single entry or as one entry per chunk
+fn read_entries(path: &str, options: &Options) -> Result<Vec<IndexedData>, CliError> {
+    let data = read_file(path)?;
+    let Some(chunk_size) = options.chunk_size else {
+        return Ok(vec![IndexedData::new(0, data)]);
+    };
+    data.chunks(chunk_size)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let index = Index::try_from(index).map_err(|_| SDDEError::LengthOverflow)?;
+            Ok(IndexedData::new(index, chunk.to_vec()))
+        })
+        .collect()
+}
+
+fn write_changes(changes: &[TreeChange], out: &mut impl Write) -> Result<(), CliError> {
+    for change in changes {
+        let result = match change {
+            TreeChange::Added(path) => writeln!(out, "added {}", path),
+            TreeChange::Removed(path) => writeln!(out, "removed {}", path),
+            TreeChange::Modified(path) => writeln!(out, "modified {}", path),
+            TreeChange::Renamed { from, to } => writeln!(out, "renamed {} -> {}", from, to),
+        };
+        result.map_err(|error| CliError::Io("stdout".to_owned(), error))?;
+    }
+    Ok(())
+}
+
+fn write_output(options: &Options, bytes: &[u8], out: &mut impl Write) -> Result<(), CliError> {
+    match options.output.as_deref() {
+        Some(path) => std::fs::write(path, bytes).map_err(|error| CliError::Io(path.to_owned(), error)),
+        None => out.write_all(bytes).map_err(|error| CliError::Io("stdout".to_owned(), error)),
+    }
+}
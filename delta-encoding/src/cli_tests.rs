@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod cli {
+    use std::path::PathBuf;
+
+    use crate::*;
+
+    /// Empty directory for the files of a test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sdde_cli_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn run(args: &[&str]) -> (i32, Vec<u8>) {
+        let mut out = Vec::new();
+        let code = run_cli(args.iter().map(|x| x.to_string()), &mut out);
+        (code, out)
+    }
+
+    fn path(dir: &std::path::Path, name: &str) -> String {
+        dir.join(name).to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn diff_and_apply_files() {
+        let dir = test_dir("diff_and_apply");
+        let (old, new, patch, out) = (path(&dir, "old"), path(&dir, "new"), path(&dir, "patch"), path(&dir, "out"));
+        std::fs::write(&old, "The quick brown fox jumps over the lazy dog".repeat(20)).unwrap();
+        std::fs::write(&new, "The quick red fox jumps over the lazy cat".repeat(21)).unwrap();
+
+        for chunk_options in [vec![], vec!["--chunk-size", "64"]] {
+            let (code, _) = run(&[&["diff", &old, &new, "-o", &patch, "--algorithm", "lcs", "--checksum", "blake3"], &chunk_options[..]].concat());
+            assert_eq!(code, 0);
+            let (code, _) = run(&[&["apply", &old, &patch, "-o", &out], &chunk_options[..]].concat());
+            assert_eq!(code, 0);
+            assert_eq!(std::fs::read(&out).unwrap(), std::fs::read(&new).unwrap());
+
+            let (code, output) = run(&[&["verify", &old, &patch], &chunk_options[..]].concat());
+            assert_eq!(code, 0);
+            assert!(String::from_utf8(output).unwrap().starts_with("ok"));
+        }
+
+        // the patch is written to stdout without -o
+        let (code, output) = run(&["diff", &old, &new]);
+        assert_eq!(code, 0);
+        assert!(PatchInspector::new(&output).is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn inspect_patch_file() {
+        let dir = test_dir("inspect");
+        let (old, new, patch) = (path(&dir, "old"), path(&dir, "new"), path(&dir, "patch"));
+        std::fs::write(&old, "Hello World").unwrap();
+        std::fs::write(&new, "Hello brave World").unwrap();
+        assert_eq!(run(&["diff", &old, &new, "-o", &patch, "--algorithm", "lcs"]).0, 0);
+        let (code, output) = run(&["inspect", &patch]);
+        assert_eq!(code, 0);
+        assert!(String::from_utf8(output).unwrap().contains("+brave"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn exit_codes() {
+        let dir = test_dir("exit_codes");
+        let (old, new, patch) = (path(&dir, "old"), path(&dir, "new"), path(&dir, "patch"));
+        std::fs::write(&old, "Hello World").unwrap();
+        std::fs::write(&new, "Hello brave World").unwrap();
+        assert_eq!(run(&["diff", &old, &new, "-o", &patch]).0, 0);
+
+        assert_eq!(run(&[]).0, EXIT_USAGE);
+        assert_eq!(run(&["merge", &old, &new]).0, EXIT_USAGE);
+        assert_eq!(run(&["diff", &old]).0, EXIT_USAGE);
+        assert_eq!(run(&["diff", &old, &new, "--chunk-size", "0"]).0, EXIT_USAGE);
+        assert_eq!(run(&["diff", &old, &new, "--algorithm", "unknown"]).0, EXIT_USAGE);
+        assert_eq!(run(&["apply", &path(&dir, "missing"), &patch]).0, EXIT_IO_ERROR);
+
+        // the patch was created for the old file, not for the new file
+        let crc_error = SDDEError::CRC(String::new());
+        assert_eq!(run(&["verify", &new, &patch]).0, exit_code(&crc_error));
+        assert_eq!(run(&["apply", &old, &patch, "--chunk-size", "4"]).0, exit_code(&crc_error));
+
+        let bytes = std::fs::read(&patch).unwrap();
+        std::fs::write(&patch, &bytes[..bytes.len() - 1]).unwrap();
+        assert_eq!(run(&["inspect", &patch]).0, exit_code(&SDDEError::Truncated { offset: 0 }));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn diff_and_apply_directories() {
+        let dir = test_dir("directories");
+        let (old, new, target, patch) = (path(&dir, "old"), path(&dir, "new"), path(&dir, "target"), path(&dir, "patch"));
+        for (root, content) in [(&old, "listen = 8080"), (&new, "listen = 9090")] {
+            std::fs::create_dir_all(format!("{}/conf", root)).unwrap();
+            std::fs::write(format!("{}/conf/app.toml", root), content).unwrap();
+        }
+        std::fs::write(format!("{}/added.txt", new), "new file").unwrap();
+
+        assert_eq!(run(&["diff", &old, &new, "-o", &patch, "--chunk-size", "4"]).0, EXIT_USAGE);
+        assert_eq!(run(&["diff", &old, &new, "-o", &patch]).0, 0);
+        let (code, output) = run(&["verify", &old, &patch]);
+        assert_eq!(code, 0);
+        assert!(String::from_utf8(output).unwrap().contains("modified conf/app.toml\nadded added.txt\n"));
+
+        let (code, _) = run(&["apply", &old, &patch, "-o", &target]);
+        assert_eq!(code, 0);
+        assert_eq!(read_tree(std::path::Path::new(&target)).unwrap(), read_tree(std::path::Path::new(&new)).unwrap());
+        assert_eq!(run(&["apply", &old, &patch]).0, 0);
+        assert_eq!(run(&["apply", &old, &patch]).0, exit_code(&SDDEError::CRC(String::new())));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,408 @@
+use std::collections::BTreeMap;
+
+use crate::data_difference::{Difference, DifferenceAction, Range};
+use crate::diff_algorithm::diff_algorithm_from_id;
+use crate::patch_format::{
+    copy_record, diff_record, index_record, PatchHeader, PATCH_VERSION, RECORD_ENTRY, RECORD_MAP_NAME,
+    RECORD_REMOVE_ENTRY, RECORD_REMOVE_MAP_NAME,
+};
+use crate::{EntryDifference, Index, SDDEError, SimpleDirectDeltaEncoding};
+
+/// Combine two consecutive patches into a single patch with the same result
+///
+///
+/// The second patch has to be created for the state after the first patch (the target CRC of the first patch is the CRC of the second patch).<br/>
+/// The differences are combined per index without the data, the bytes which are kept from the data before the first patch are referenced by their position.<br/>
+/// Only patches of the built-in diff algorithms with the same checksum algorithm can be composed, the composed patch is not reversible.
+///
+///
+/// A copy of an entry which is changed by an earlier patch is only composable if the entry is known to exist before the first patch
+/// or is created from empty data (reversible patches contain this information).
+pub fn compose(patch_a: &[u8], patch_b: &[u8]) -> Result<Vec<u8>, SDDEError> {
+    let (header_a, _) = PatchHeader::from_bytes(patch_a)?;
+    let (header_b, _) = PatchHeader::from_bytes(patch_b)?;
+    if header_a.version == 0 || header_b.version == 0 {
+        return Err(SDDEError::NotComposable("Version 0 patches have no target CRC".to_owned()));
+    }
+    if header_a.checksum_algorithm != header_b.checksum_algorithm {
+        return Err(SDDEError::NotComposable("The patches have different checksum algorithms".to_owned()));
+    }
+    if header_a.target_crc != header_b.crc {
+        return Err(SDDEError::NotComposable(
+            "The second patch is not created for the result of the first patch".to_owned(),
+        ));
+    }
+    // custom algorithms can apply the differences in a different way
+    for header in [&header_a, &header_b] {
+        if diff_algorithm_from_id(header.diff_algorithm).is_none() {
+            return Err(SDDEError::UnknownDiffAlgorithm { id: header.diff_algorithm });
+        }
+    }
+
+    let mut composed = ComposedPatch::default();
+    composed.apply(&SimpleDirectDeltaEncoding::get_differences(patch_a)?)?;
+    composed.apply(&SimpleDirectDeltaEncoding::get_differences(patch_b)?)?;
+
+    let header = PatchHeader {
+        version: PATCH_VERSION,
+        diff_algorithm: header_b.diff_algorithm,
+        checksum_algorithm: header_a.checksum_algorithm,
+        crc: header_a.crc,
+        target_crc: header_b.target_crc,
+    };
+    Ok([header.to_bytes(), composed.to_records()].concat())
+}
+
+/// Part of a composed value
+#[derive(Debug, Clone)]
+pub(crate) enum Piece {
+    Literal(Vec<u8>),
+    /// Bytes of the value before the first patch
+    Base { start: usize, length: usize },
+}
+
+impl Piece {
+    pub(crate) fn length(&self) -> usize {
+        match self {
+            Piece::Literal(value) => value.len(),
+            Piece::Base { length, .. } => *length,
+        }
+    }
+
+    fn split_at(&self, position: usize) -> (Piece, Piece) {
+        match self {
+            Piece::Literal(value) => (
+                Piece::Literal(value[..position].to_vec()),
+                Piece::Literal(value[position..].to_vec()),
+            ),
+            Piece::Base { start, length } => (
+                Piece::Base { start: *start, length: position },
+                Piece::Base {
+                    start: start + position,
+                    length: length - position,
+                },
+            ),
+        }
+    }
+}
+
+/// Change of the value before the first patch, the old bytes of the range are replaced by the pieces
+#[derive(Debug)]
+pub(crate) struct Edit {
+    pub(crate) range: Range,
+    pub(crate) pieces: Vec<Piece>,
+}
+
+/// Value (data or index mapping name) as pieces of new bytes and bytes of the value before the first patch
+#[derive(Debug, Clone)]
+pub(crate) struct ComposedValue {
+    pieces: Vec<Piece>,
+    /// Position in the value before the first patch from which all bytes follow the pieces (`None` if nothing follows)
+    tail: Option<usize>,
+}
+
+impl ComposedValue {
+    /// The value before the first patch (its length is not known)
+    pub(crate) fn unchanged() -> ComposedValue {
+        ComposedValue {
+            pieces: Vec::new(),
+            tail: Some(0),
+        }
+    }
+
+    fn empty() -> ComposedValue {
+        ComposedValue {
+            pieces: Vec::new(),
+            tail: None,
+        }
+    }
+
+    /// Position of the piece which starts at the position, the pieces are split or taken from the tail as needed
+    ///
+    ///
+    /// Returns `None` if the position is after the end of the value or the tail position overflows.
+    fn split(&mut self, position: usize) -> Option<usize> {
+        let mut offset: usize = 0;
+        for i in 0..self.pieces.len() {
+            let length = self.pieces[i].length();
+            if position == offset {
+                return Some(i);
+            }
+            if position < offset.checked_add(length)? {
+                let (first, second) = self.pieces[i].split_at(position - offset);
+                self.pieces[i] = first;
+                self.pieces.insert(i + 1, second);
+                return Some(i + 1);
+            }
+            offset += length;
+        }
+        let missing = position - offset;
+        if missing > 0 {
+            let tail = self.tail?;
+            self.pieces.push(Piece::Base { start: tail, length: missing });
+            self.tail = Some(tail.checked_add(missing)?);
+        }
+        Some(self.pieces.len())
+    }
+
+    fn slice(&self, start: usize, length: usize) -> Option<Vec<Piece>> {
+        let mut value = self.clone();
+        let first = value.split(start)?;
+        let last = value.split(start.checked_add(length)?)?;
+        Some(value.pieces[first..last].to_vec())
+    }
+
+    /// Applies the differences like [`crate::DataDifference::apply_diff`], copies refer to the value before the differences
+    pub(crate) fn apply(&mut self, index: Index, diffs: &[Difference]) -> Result<(), SDDEError> {
+        let source = self.clone();
+        for d in diffs {
+            let out_of_bounds = || SDDEError::RangeOutOfBounds {
+                index: Some(index),
+                range: d.range.clone(),
+            };
+            let mut end = d.range.start.checked_add(d.range.length).ok_or_else(out_of_bounds)?;
+            let replacement = match d.action {
+                DifferenceAction::Replace if d.value.len() != d.range.length => return Err(out_of_bounds()),
+                DifferenceAction::Replace => vec![Piece::Literal(d.value.clone())],
+                DifferenceAction::Insert => {
+                    end = d.range.start;
+                    vec![Piece::Literal(d.value.clone())]
+                },
+                DifferenceAction::Delete => Vec::new(),
+                DifferenceAction::Copy { src_start, len } => source.slice(src_start, len).ok_or_else(out_of_bounds)?,
+            };
+            let first = self.split(d.range.start).ok_or_else(out_of_bounds)?;
+            let last = self.split(end).ok_or_else(out_of_bounds)?;
+            self.pieces.splice(first..last, replacement.into_iter().filter(|x| x.length() > 0));
+            // copies of large old ranges can grow the value beyond the positions
+            self.length().ok_or_else(out_of_bounds)?;
+        }
+        Ok(())
+    }
+
+    /// Length of the pieces (`None` if it overflows)
+    fn length(&self) -> Option<usize> {
+        self.pieces.iter().try_fold(0usize, |length, piece| length.checked_add(piece.length()))
+    }
+
+    /// Changes of the value before the first patch in order
+    ///
+    ///
+    /// Bytes of the old value which are in order are kept, the other bytes of the old value are copies in the pieces of an edit.
+    pub(crate) fn edits(&self) -> Vec<Edit> {
+        let mut edits: Vec<Edit> = Vec::new();
+        // old bytes before this position are kept or replaced by an edit
+        let mut base_position = 0;
+        let mut pieces: Vec<Piece> = Vec::new();
+        for piece in self.merged_pieces() {
+            match piece {
+                Piece::Base { start, length }
+                    if start >= base_position
+                        && self.tail.is_some_and(|tail| start.checked_add(length).is_some_and(|end| end <= tail)) =>
+                {
+                    if start > base_position || !pieces.is_empty() {
+                        edits.push(Edit {
+                            range: Range::new(base_position, start - base_position),
+                            pieces: std::mem::take(&mut pieces),
+                        });
+                    }
+                    base_position = start + length;
+                },
+                piece => pieces.push(piece),
+            }
+        }
+        let removed = self.tail.map(|tail| tail.saturating_sub(base_position)).unwrap_or_default();
+        if removed > 0 || !pieces.is_empty() {
+            edits.push(Edit {
+                range: Range::new(base_position, removed),
+                pieces,
+            });
+        }
+        edits
+    }
+
+    /// Differences which create the value from the value before the first patch
+    fn to_differences(&self) -> Vec<Difference> {
+        let mut differences: Vec<Difference> = Vec::new();
+        // position in the data with the previous differences applied
+        let mut position = 0;
+        let mut base_position = 0;
+        for edit in self.edits() {
+            position += edit.range.start - base_position;
+            base_position = edit.range.start + edit.range.length;
+            if edit.range.length > 0 {
+                differences.push(difference(DifferenceAction::Delete, position, edit.range.length, Vec::new()));
+            }
+            for piece in edit.pieces {
+                let length = piece.length();
+                differences.push(match piece {
+                    Piece::Literal(value) => difference(DifferenceAction::Insert, position, length, value),
+                    Piece::Base { start, length } => {
+                        difference(DifferenceAction::Copy { src_start: start, len: length }, position, 0, Vec::new())
+                    },
+                });
+                position += length;
+            }
+        }
+        differences
+    }
+
+    /// Pieces with the adjacent literals and old bytes combined
+    fn merged_pieces(&self) -> Vec<Piece> {
+        let mut pieces: Vec<Piece> = Vec::with_capacity(self.pieces.len());
+        for piece in self.pieces.iter() {
+            match (pieces.last_mut(), piece) {
+                (Some(Piece::Literal(last)), Piece::Literal(value)) => last.extend(value),
+                (Some(Piece::Base { start, length }), Piece::Base { start: next, length: next_length })
+                    if *start + *length == *next =>
+                {
+                    *length += next_length;
+                },
+                _ => pieces.push(piece.clone()),
+            }
+        }
+        pieces
+    }
+}
+
+pub(crate) fn difference(action: DifferenceAction, start: usize, length: usize, value: Vec<u8>) -> Difference {
+    Difference {
+        action,
+        range: Range::new(start, length),
+        value,
+        is_open: false,
+    }
+}
+
+/// Data entry or index mapping of an index after the composed patches
+#[derive(Debug, Clone)]
+enum ComposedEntry {
+    Removed,
+    /// `base` is the index of the value before the first patch (`None` if the value is created from empty data)<br/>
+    /// `existed` is true if the base value is known to exist before the first patch (only then it can be the source of a copy)
+    Value {
+        base: Option<Index>,
+        existed: bool,
+        value: ComposedValue,
+    },
+}
+
+impl ComposedEntry {
+    fn unchanged(index: Index, existed: bool) -> ComposedEntry {
+        ComposedEntry::Value {
+            base: Some(index),
+            existed,
+            value: ComposedValue::unchanged(),
+        }
+    }
+
+    fn empty() -> ComposedEntry {
+        ComposedEntry::Value {
+            base: None,
+            existed: false,
+            value: ComposedValue::empty(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ComposedPatch {
+    entries: BTreeMap<Index, ComposedEntry>,
+    map_names: BTreeMap<Index, ComposedEntry>,
+}
+
+impl ComposedPatch {
+    /// Applies the differences of the next patch to the composed entries and index mappings
+    fn apply(&mut self, diffs: &BTreeMap<Index, EntryDifference>) -> Result<(), SDDEError> {
+        // copies refer to the data before the patch
+        let previous = self.entries.clone();
+        for (index, diff) in diffs.iter() {
+            if diff.data_changed {
+                let entry = if let Some(source_index) = diff.copy_from {
+                    // an unchanged source exists before the first patch, because it exists before this patch
+                    match previous.get(&source_index) {
+                        None => ComposedEntry::unchanged(source_index, true),
+                        Some(ComposedEntry::Removed) => return Err(SDDEError::UnknownSourceIndex { index: source_index }),
+                        // a value created from empty data is copied as a whole
+                        Some(entry @ ComposedEntry::Value { base: None, .. }) => entry.clone(),
+                        Some(entry @ ComposedEntry::Value { existed: true, .. }) => entry.clone(),
+                        Some(ComposedEntry::Value { .. }) => {
+                            return Err(SDDEError::NotComposable(format!(
+                                "The copy source {} may not exist before the first patch",
+                                source_index
+                            )))
+                        },
+                    }
+                } else if diff.remove_entry {
+                    ComposedEntry::empty()
+                } else {
+                    // the removed bytes of a reversible patch tell if the data exists before the patch
+                    let existed = diff.old_data.as_ref().map(|old_data| old_data.existed);
+                    match previous.get(index) {
+                        None if existed == Some(false) => ComposedEntry::empty(),
+                        None => ComposedEntry::unchanged(*index, existed.unwrap_or_default()),
+                        Some(ComposedEntry::Removed) => ComposedEntry::empty(),
+                        Some(entry) => entry.clone(),
+                    }
+                };
+                self.entries.insert(*index, Self::apply_entry(*index, entry, &diff.diffs)?);
+            } else if diff.remove_entry {
+                self.entries.insert(*index, ComposedEntry::Removed);
+            }
+
+            if diff.map_name_removed {
+                self.map_names.insert(*index, ComposedEntry::Removed);
+            }
+            if let Some(map_name_changed) = diff.map_name_changed.as_ref() {
+                let entry = match self.map_names.get(index) {
+                    Some(ComposedEntry::Removed) => ComposedEntry::empty(),
+                    Some(entry) => entry.clone(),
+                    None => ComposedEntry::unchanged(*index, false),
+                };
+                self.map_names.insert(*index, Self::apply_entry(*index, entry, map_name_changed)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_entry(index: Index, entry: ComposedEntry, diffs: &[Difference]) -> Result<ComposedEntry, SDDEError> {
+        let ComposedEntry::Value { base, existed, mut value } = entry else {
+            return Ok(entry);
+        };
+        value.apply(index, diffs)?;
+        Ok(ComposedEntry::Value { base, existed, value })
+    }
+
+    fn to_records(&self) -> Vec<u8> {
+        let mut records: Vec<u8> = Vec::new();
+        for (index, entry) in self.entries.iter() {
+            match entry {
+                ComposedEntry::Removed => records.extend(index_record(RECORD_REMOVE_ENTRY, *index)),
+                ComposedEntry::Value { base: None, value, .. } => {
+                    records.extend(index_record(RECORD_REMOVE_ENTRY, *index));
+                    records.extend(diff_record(RECORD_ENTRY, *index, &value.to_differences()));
+                },
+                // the record is kept without differences, it creates the entry if the index has no data yet
+                ComposedEntry::Value { base: Some(base), value, .. } if base == index => {
+                    records.extend(diff_record(RECORD_ENTRY, *index, &value.to_differences()));
+                },
+                ComposedEntry::Value { base: Some(base), value, .. } => {
+                    records.extend(copy_record(*index, *base, &value.to_differences()));
+                },
+            }
+        }
+        for (index, entry) in self.map_names.iter() {
+            match entry {
+                ComposedEntry::Removed => records.extend(index_record(RECORD_REMOVE_MAP_NAME, *index)),
+                ComposedEntry::Value { base: None, value, .. } => {
+                    records.extend(index_record(RECORD_REMOVE_MAP_NAME, *index));
+                    records.extend(diff_record(RECORD_MAP_NAME, *index, &value.to_differences()));
+                },
+                ComposedEntry::Value { base: Some(_), value, .. } => {
+                    records.extend(diff_record(RECORD_MAP_NAME, *index, &value.to_differences()));
+                },
+            }
+        }
+        records
+    }
+}
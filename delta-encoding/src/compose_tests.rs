@@ -0,0 +1,209 @@
+#[cfg(test)]
+mod compose {
+    use std::collections::BTreeMap;
+
+    use crate::tests::props;
+    use crate::*;
+
+    fn assert_composed(props: &[IndexedData], patches: &[Vec<u8>], sender: &SimpleDirectDeltaEncoding) {
+        let mut composed = patches[0].clone();
+        for patch in patches.iter().skip(1) {
+            composed = compose(&composed, patch).unwrap();
+        }
+        let mut receiver = SimpleDirectDeltaEncoding::new(props);
+        let result = receiver.apply_patch(&composed);
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(receiver.get_state(), sender.get_state());
+    }
+
+    #[test]
+    fn compose_two_patches() {
+        let props = props();
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        sender.set_diff_algorithm(LcsDiff::default());
+        let patch_a = sender.patch(&[
+            IndexedData::new(0, "Hello new World".as_bytes().to_vec()),
+            IndexedData::new(1, "Test 1".as_bytes().to_vec()),
+            IndexedData::new(2, "abcdefghijklmnopqrstuvwxyz".as_bytes().to_vec()),
+        ]);
+        let patch_b = sender.patch(&[
+            IndexedData::new(0, "Hello brave new World!".as_bytes().to_vec()),
+            IndexedData::new(1, "Test 2".as_bytes().to_vec()),
+            IndexedData::new(2, "abcdefghijklmnopqrstuvwxyz".as_bytes().to_vec()),
+        ]);
+        assert_composed(&props, &[patch_a, patch_b], &sender);
+    }
+
+    #[test]
+    fn compose_removed_and_added_entries() {
+        let props = props();
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        let patch_a = sender.patch(&[
+            IndexedData::new(0, "Hello World".as_bytes().to_vec()),
+            IndexedData::new(3, "new".as_bytes().to_vec()),
+        ]);
+        // the removed index 1 is created again and the new index 3 is emptied
+        let patch_b = sender.patch(&[
+            IndexedData::new(0, "Hello World".as_bytes().to_vec()),
+            IndexedData::new(1, "again".as_bytes().to_vec()),
+            IndexedData::new(3, Vec::new()),
+        ]);
+        let composed = compose(&patch_a, &patch_b).unwrap();
+        let diffs = SimpleDirectDeltaEncoding::get_differences(&composed).unwrap();
+        assert!(diffs[&1].remove_entry && diffs[&1].data_changed);
+        assert!(diffs[&2].remove_entry && !diffs[&2].data_changed);
+        assert_composed(&props, &[patch_a, patch_b], &sender);
+    }
+
+    #[test]
+    fn compose_moved_data() {
+        let props = props();
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        sender.set_diff_algorithm(BlockDiff::new(4));
+        // the data of index 2 is moved to index 3 and moved inside of the value afterwards
+        let patch_a = sender.patch(&[
+            IndexedData::new(0, "Hello World".as_bytes().to_vec()),
+            IndexedData::new(1, "Test 1".as_bytes().to_vec()),
+            IndexedData::new(3, "abcdefghijklmnopqrstuvwxyz".as_bytes().to_vec()),
+        ]);
+        let patch_b = sender.patch(&[
+            IndexedData::new(0, "Hello World".as_bytes().to_vec()),
+            IndexedData::new(1, "Test 1".as_bytes().to_vec()),
+            IndexedData::new(3, "nopqrstuvwxyz-abcdefghijklm".as_bytes().to_vec()),
+        ]);
+        let composed = compose(&patch_a, &patch_b).unwrap();
+        assert_eq!(SimpleDirectDeltaEncoding::get_differences(&composed).unwrap()[&3].copy_from, Some(2));
+        assert_composed(&props, &[patch_a, patch_b], &sender);
+    }
+
+    #[test]
+    fn compose_index_mappings() {
+        let props = props();
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        sender.change_index_mapping(0, "greeting".as_bytes());
+        sender.change_index_mapping(1, "test".as_bytes());
+        let patch_a = sender.patch(&props);
+        sender.change_index_mapping(0, "greetings".as_bytes());
+        sender.remove_index_mapping(1);
+        sender.change_index_mapping(2, "letters".as_bytes());
+        let patch_b = sender.patch(&props);
+        sender.change_index_mapping(1, "test again".as_bytes());
+        sender.remove_index_mapping(2);
+        let patch_c = sender.patch(&props);
+        assert_composed(&props, &[patch_a, patch_b, patch_c], &sender);
+    }
+
+    #[test]
+    fn compose_many_patches() {
+        let props = props();
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        let mut current: BTreeMap<Index, Vec<u8>> = props.iter().map(|x| (x.index, x.data.clone())).collect();
+        let mut patches = Vec::new();
+        for round in 0..30u32 {
+            match round % 3 {
+                0 => sender.set_diff_algorithm(PositionalDiff::default()),
+                1 => sender.set_diff_algorithm(LcsDiff::default()),
+                _ => sender.set_diff_algorithm(BlockDiff::new(4)),
+            }
+            let index = round * 7 % 5;
+            match round % 4 {
+                0 => {
+                    current.remove(&index);
+                },
+                1 => {
+                    let value = current.get(&((index + 1) % 5)).cloned().unwrap_or_default();
+                    current.insert(index, [value, format!("({})", round).into_bytes()].concat());
+                },
+                _ => {
+                    let value = current.entry(index).or_default();
+                    let position = (round as usize * 3) % (value.len() + 1);
+                    value.splice(position..position, format!("<{}>", round).into_bytes());
+                    let end = value.len().min(position + 8);
+                    value.drain(position.min(end)..end.saturating_sub(2));
+                },
+            }
+            if round % 5 == 0 {
+                sender.change_index_mapping(index, format!("key {}", round).as_bytes());
+            }
+            if round % 7 == 3 {
+                sender.remove_index_mapping((index + 2) % 5);
+            }
+            let new_data: Vec<IndexedData> = current.iter().map(|(index, data)| IndexedData::new(*index, data.clone())).collect();
+            patches.push(sender.patch(&new_data));
+        }
+        assert_composed(&props, &patches, &sender);
+    }
+
+    #[test]
+    fn compose_not_consecutive_patches() {
+        let props = props();
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        let patch_a = sender.patch(&[IndexedData::new(0, "a".as_bytes().to_vec())]);
+        let patch_b = sender.patch(&[IndexedData::new(0, "b".as_bytes().to_vec())]);
+        assert!(matches!(compose(&patch_b, &patch_a), Err(SDDEError::NotComposable(_))));
+    }
+
+    #[test]
+    fn compose_different_checksum_algorithms() {
+        let props = props();
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        let patch_a = sender.patch(&[IndexedData::new(0, "a".as_bytes().to_vec())]);
+        sender.set_checksum_algorithm(ChecksumAlgorithm::Crc32c);
+        let patch_b = sender.patch(&[IndexedData::new(0, "b".as_bytes().to_vec())]);
+        assert!(matches!(compose(&patch_a, &patch_b), Err(SDDEError::NotComposable(_))));
+    }
+
+    #[test]
+    fn compose_copy_of_added_entry() {
+        let props = vec![IndexedData::new(0, "a".as_bytes().to_vec())];
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        sender.set_reversible(true);
+        let patch_a = sender.patch(&[
+            IndexedData::new(0, "a".as_bytes().to_vec()),
+            IndexedData::new(3, "hello".as_bytes().to_vec()),
+        ]);
+        // the new index 4 is a copy of the index 3 which does not exist before the first patch
+        let patch_b = sender.patch(&[
+            IndexedData::new(0, "a".as_bytes().to_vec()),
+            IndexedData::new(3, "hello".as_bytes().to_vec()),
+            IndexedData::new(4, "hello".as_bytes().to_vec()),
+        ]);
+        assert_composed(&props, &[patch_a, patch_b], &sender);
+    }
+
+    #[test]
+    fn compose_copy_of_unknown_entry() {
+        // without the removed bytes it is not known if the changed index 3 exists before the first patch
+        let props = vec![IndexedData::new(0, "a".as_bytes().to_vec())];
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        let patch_a = sender.patch(&[
+            IndexedData::new(0, "a".as_bytes().to_vec()),
+            IndexedData::new(3, "hello".as_bytes().to_vec()),
+        ]);
+        let patch_b = sender.patch(&[
+            IndexedData::new(0, "a".as_bytes().to_vec()),
+            IndexedData::new(3, "hello".as_bytes().to_vec()),
+            IndexedData::new(4, "hello".as_bytes().to_vec()),
+        ]);
+        assert!(matches!(compose(&patch_a, &patch_b), Err(SDDEError::NotComposable(_))));
+    }
+
+    #[test]
+    fn compose_ranges_out_of_bounds() {
+        let delete = |index: Index, length: usize| {
+            diff_record(RECORD_ENTRY, index, &[Difference {
+                action: DifferenceAction::Delete,
+                range: Range::new(0, length),
+                value: vec![],
+                is_open: false,
+            }])
+        };
+        let mut header_a = PatchHeader::new(PositionalDiff::ID, vec![1]);
+        header_a.target_crc = vec![2];
+        let mut header_b = PatchHeader::new(PositionalDiff::ID, vec![2]);
+        header_b.target_crc = vec![3];
+        let patch_a = [header_a.to_bytes(), delete(0, usize::MAX - 10)].concat();
+        let patch_b = [header_b.to_bytes(), delete(0, 100)].concat();
+        assert!(matches!(compose(&patch_a, &patch_b), Err(SDDEError::RangeOutOfBounds { index: Some(0), .. })));
+    }
+}
@@ -1,10 +1,23 @@
+use std::collections::HashMap;
+
+use crate::patch_format::{ByteCursor, PatchSource};
 use crate::SDDEError;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DifferenceAction {
     Replace,
     Insert,
     Delete,
+    /// Replaces the range with `len` bytes of the old data starting at `src_start` (the data the differences are applied to)
+    Copy { src_start: usize, len: usize },
+}
+
+impl DifferenceAction {
+    /// Actions which carry the new bytes in the value of the difference
+    pub fn has_value(&self) -> bool {
+        matches!(self, DifferenceAction::Replace | DifferenceAction::Insert)
+    }
 }
 
 impl From<DifferenceAction> for u8 {
@@ -13,6 +26,7 @@ impl From<DifferenceAction> for u8 {
             DifferenceAction::Replace => b'r',
             DifferenceAction::Insert => b'i',
             DifferenceAction::Delete => b'd',
+            DifferenceAction::Copy { .. } => b'c',
         }
     }
 }
@@ -25,6 +39,8 @@ impl TryInto<DifferenceAction> for u8 {
             b'r' => Ok(DifferenceAction::Replace),
             b'i' => Ok(DifferenceAction::Insert),
             b'd' => Ok(DifferenceAction::Delete),
+            // the source range follows the action byte and is read by the decoder
+            b'c' => Ok(DifferenceAction::Copy { src_start: 0, len: 0 }),
             _ => Err(SDDEError::DifferenceInvalid(format!("Invalid difference action. Byte: {:?}", self)))
         }
     }
@@ -32,6 +48,7 @@ impl TryInto<DifferenceAction> for u8 {
 
 /// range indicator start-length (in byte conversion a prefix is used for the usize type [implicit if no other short key matches it is default u8 value])
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Range {
     pub start: usize,
     pub length: usize,
@@ -81,6 +98,7 @@ impl From<USizeType> for u8 {
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Difference {
     pub action: DifferenceAction,
     pub range: Range,
@@ -108,51 +126,56 @@ impl Difference {
         diff.extend(Self::get_usize_type_to_bytes(self.range.start));
         diff.push(b'-');
         diff.extend(Self::get_usize_type_to_bytes(self.range.length));
-        if self.action != DifferenceAction::Delete {
+        if self.action.has_value() {
             diff.extend(self.value.to_owned());
         }
         diff
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Self {
-        let action: DifferenceAction = bytes[0].try_into().ok().unwrap();
-        let range_start = Self::get_usize_type_from_bytes(&bytes[2..]);
-        let offset = 2 + range_start.1 + 1; // 2 bytes for action and range start, range start bytes count, 1 byte for range separator
-        let range_end = Self::get_usize_type_from_bytes(&bytes[offset..]);
-        let offset = offset + range_end.1;
-        let value = bytes[offset..].to_vec();
-        let is_open = false;
-        Self {
-            action,
-            range: Range::new(range_start.0, range_end.0),
-            value,
-            is_open,
-        }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SDDEError> {
+        Self::read_legacy(&mut ByteCursor::new(bytes))
     }
 
+    #[deprecated(note = "use `Difference::from_bytes` which validates the bytes")]
     pub fn validate_from_bytes(bytes: &[u8]) -> Result<Self, SDDEError> {
-        let action_byte = bytes[0];
-        let action_result = action_byte.try_into();
-        if action_result.is_err() {
-            return Err(action_result.err().unwrap());
-        }
+        Self::from_bytes(bytes)
+    }
 
-        let range_start = Self::get_usize_type_from_bytes(&bytes[2..]);
-        let offset = 2 + range_start.1 + 1; // 2 bytes for action and range start, range start bytes count, 1 byte for range separator
-        let range_end = Self::get_usize_type_from_bytes(&bytes[offset..]);
-        let offset = offset + range_end.1;
-        let value = bytes[offset..].to_vec();
+    /// Reads a difference of the version 0 format, the value takes all remaining bytes of the cursor
+    pub(crate) fn read_legacy(cursor: &mut ByteCursor) -> Result<Self, SDDEError> {
+        let offset = cursor.offset();
+        let action = Self::read_action(cursor)?;
+        if let DifferenceAction::Copy { .. } = action {
+            // the version 0 format has no copy action
+            return Err(SDDEError::UnknownControlByte { byte: action.into(), offset });
+        }
+        Self::read_separator(cursor, b':')?;
+        let range_start = Self::read_usize_type(cursor)?;
+        Self::read_separator(cursor, b'-')?;
+        let range_end = Self::read_usize_type(cursor)?;
+        let value = cursor.read_to_end().to_vec();
         let is_open = false;
         Ok(Self {
-            action: action_result.ok().unwrap(),
-            range: Range::new(range_start.0, range_end.0),
+            action,
+            range: Range::new(range_start, range_end),
             value,
             is_open,
         })
     }
 
+    fn read_separator(cursor: &mut ByteCursor, separator: u8) -> Result<(), SDDEError> {
+        let offset = cursor.offset();
+        let byte = cursor.read_u8()?;
+        if byte != separator {
+            return Err(SDDEError::UnknownControlByte { byte, offset });
+        }
+        Ok(())
+    }
+
     pub fn get_usize_type_to_bytes(value: usize) -> Vec<u8> {
-        if value <= 255 {
+        // single byte values which collide with a type prefix are written as u16
+        let is_prefix = USizeType::from(value as u8) != USizeType::U8;
+        if value <= 255 && !is_prefix {
             (value as u8).to_be_bytes().to_vec()
         } else if value <= 65_535 {
             [vec![USizeType::U16.into()], (value as u16).to_be_bytes().to_vec()].concat()
@@ -164,28 +187,129 @@ impl Difference {
     }
 
     /// Returns the usize value and the bytes count of the usize type
-    pub fn get_usize_type_from_bytes(bytes: &[u8]) -> (usize, usize) {
-        match bytes[0].into() {
+    pub fn get_usize_type_from_bytes(bytes: &[u8]) -> Result<(usize, usize), SDDEError> {
+        let mut cursor = ByteCursor::new(bytes);
+        let value = Self::read_usize_type(&mut cursor)?;
+        Ok((value, cursor.offset()))
+    }
+
+    pub(crate) fn read_usize_type(cursor: &mut ByteCursor) -> Result<usize, SDDEError> {
+        let prefix = cursor.read_u8()?;
+        match prefix.into() {
             USizeType::U16 => {
                 let mut buffer = [0; 2];
-                buffer.copy_from_slice(&bytes[1..3]);
-                (u16::from_be_bytes(buffer) as usize, 3)
+                buffer.copy_from_slice(cursor.read_bytes(2)?);
+                Ok(u16::from_be_bytes(buffer) as usize)
             },
             USizeType::U32 => {
                 let mut buffer = [0; 4];
-                buffer.copy_from_slice(&bytes[1..5]);
-                (u32::from_be_bytes(buffer) as usize, 5)
+                buffer.copy_from_slice(cursor.read_bytes(4)?);
+                usize::try_from(u32::from_be_bytes(buffer)).map_err(|_| SDDEError::LengthOverflow)
             },
             USizeType::U64 => {
                 let mut buffer = [0; 8];
-                buffer.copy_from_slice(&bytes[1..9]);
-                (u64::from_be_bytes(buffer) as usize, 9)
+                buffer.copy_from_slice(cursor.read_bytes(8)?);
+                usize::try_from(u64::from_be_bytes(buffer)).map_err(|_| SDDEError::LengthOverflow)
             },
-            _ => (bytes[0] as usize, 1)
+            _ => Ok(prefix as usize)
         }
     }
 }
 
+/// Common part of the old and new data (old start, new start, length)
+type Segment = (usize, usize, usize);
+
+/// Maximum edit distance the Myers diff searches for a middle snake, longer distances are handled as replace
+const MYERS_MAX_COST: usize = 4096;
+
+/// Minimum length of a copy, shorter matches are cheaper to store as value
+const MIN_COPY_LENGTH: usize = 8;
+
+/// Maximum old blocks with the same hash which are compared with the new data
+const MAX_BLOCK_CANDIDATES: usize = 8;
+
+/// Rabin-Karp hash over a window of bytes which can be moved by one byte
+pub(crate) struct RollingHash {
+    value: u32,
+    /// BASE^(window length - 1) to remove the first byte of the window
+    power: u32,
+}
+
+impl RollingHash {
+    const BASE: u32 = 257;
+
+    pub(crate) fn new(window: &[u8]) -> RollingHash {
+        let mut value: u32 = 0;
+        let mut power: u32 = 1;
+        for (i, b) in window.iter().enumerate() {
+            value = value.wrapping_mul(Self::BASE).wrapping_add(*b as u32);
+            if i > 0 {
+                power = power.wrapping_mul(Self::BASE);
+            }
+        }
+        RollingHash { value, power }
+    }
+
+    pub(crate) fn value(&self) -> u32 {
+        self.value
+    }
+
+    /// Removes the first byte of the window and appends the next byte
+    pub(crate) fn roll(&mut self, first: u8, next: u8) {
+        self.value = self
+            .value
+            .wrapping_sub((first as u32).wrapping_mul(self.power))
+            .wrapping_mul(Self::BASE)
+            .wrapping_add(next as u32);
+    }
+}
+
+/// Changed part between two common segments while its differences are created
+struct Gap {
+    /// Position in the data with the previous differences applied
+    position: usize,
+    /// Old bytes of the change which are not replaced yet
+    deleted: usize,
+}
+
+impl Gap {
+    /// New bytes which replace the old bytes of the change as long as there are any, the rest is inserted
+    fn literal(&mut self, differences: &mut Vec<Difference>, bytes: &[u8]) {
+        let common = self.deleted.min(bytes.len());
+        if common > 0 {
+            differences.push(Difference {
+                action: DifferenceAction::Replace,
+                range: Range::new(self.position, common),
+                value: bytes[..common].to_vec(),
+                is_open: false,
+            });
+        }
+        if bytes.len() > common {
+            differences.push(Difference {
+                action: DifferenceAction::Insert,
+                range: Range::new(self.position + common, bytes.len() - common),
+                value: bytes[common..].to_vec(),
+                is_open: false,
+            });
+        }
+        self.position += bytes.len();
+        self.deleted -= common;
+    }
+
+    /// Bytes of the old data which replace the old bytes of the change as long as there are any
+    fn copy(&mut self, differences: &mut Vec<Difference>, src_start: usize, len: usize) {
+        let replaced = self.deleted.min(len);
+        differences.push(Difference {
+            action: DifferenceAction::Copy { src_start, len },
+            range: Range::new(self.position, replaced),
+            value: vec![],
+            is_open: false,
+        });
+        self.position += len;
+        self.deleted -= replaced;
+    }
+}
+
 pub struct DataDifference { }
 
 impl DataDifference {
@@ -318,17 +442,416 @@ impl DataDifference {
         differences
     }
 
-    pub fn apply_diff(data: &[u8], diff: &[Difference]) -> Vec<u8> {
-        let mut data = data.to_vec();
+    /// Differences based on the shortest edit script (Myers O(ND) with linear space)
+    ///
+    ///
+    /// The ranges of the differences are positions in the data with all previous differences applied.
+    pub fn diff_myers(old_data: &[u8], new_data: &[u8]) -> Vec<Difference> {
+        let mut segments: Vec<Segment> = Vec::new();
+        Self::lcs_segments(old_data, new_data, 0, 0, &mut segments);
+        Self::segments_to_differences(old_data.len(), new_data, &segments, &[])
+    }
+
+    /// Count of the common bytes (longest common subsequence) divided by the length of the longer data
+    ///
+    ///
+    /// Returns 1.0 for two empty values.
+    pub fn similarity(old_data: &[u8], new_data: &[u8]) -> f64 {
+        let longer = old_data.len().max(new_data.len());
+        if longer == 0 {
+            return 1.0;
+        }
+        let mut segments: Vec<Segment> = Vec::new();
+        Self::lcs_segments(old_data, new_data, 0, 0, &mut segments);
+        let common: usize = segments.iter().map(|(_, _, length)| length).sum();
+        common as f64 / longer as f64
+    }
+
+    /// Differences based on blocks of the old data which are found in the new data with a rolling hash
+    ///
+    ///
+    /// Matching blocks are extended as far as possible and the longest in order sequence of matches is kept,
+    /// the other matches (moved or duplicated data) are stored as [`DifferenceAction::Copy`] of the old data.<br/>
+    /// This is fast for large data with small changes, the changed parts without a match are replaced as a whole.
+    pub fn diff_blocks(old_data: &[u8], new_data: &[u8], block_size: usize) -> Vec<Difference> {
+        if old_data == new_data {
+            return Vec::new();
+        }
+        let block_size = block_size.max(1);
+        // the unchanged start and end are kept even if they are shorter than a block
+        let prefix = old_data.iter().zip(new_data.iter()).take_while(|(a, b)| a == b).count();
+        let suffix = old_data[prefix..]
+            .iter()
+            .rev()
+            .zip(new_data[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let old_limit = old_data.len() - suffix;
+        // the changed new data is searched in the whole old data to find moved and duplicated blocks
+        let matches: Vec<Segment> = Self::block_matches(old_data, &new_data[prefix..(new_data.len() - suffix)], block_size)
+            .into_iter()
+            .map(|(old_start, new_start, length)| (old_start, new_start + prefix, length))
+            .collect();
+
+        // keep the longest sequence of matches which is in order for the old data
+        let mut tails: Vec<usize> = Vec::new();
+        let mut previous: Vec<Option<usize>> = vec![None; matches.len()];
+        for (i, (old_start, _, _)) in matches.iter().enumerate() {
+            let position = tails.partition_point(|&t| matches[t].0 < *old_start);
+            previous[i] = position.checked_sub(1).map(|p| tails[p]);
+            if position == tails.len() {
+                tails.push(i);
+            } else {
+                tails[position] = i;
+            }
+        }
+        let mut sequence = Vec::with_capacity(tails.len());
+        let mut current = tails.last().copied();
+        while let Some(i) = current {
+            sequence.push(matches[i]);
+            current = previous[i];
+        }
+        sequence.reverse();
+
+        let mut segments: Vec<Segment> = Vec::with_capacity(sequence.len() + 2);
+        if prefix > 0 {
+            segments.push((0, 0, prefix));
+        }
+        let mut old_end: usize = prefix;
+        let mut new_end: usize = prefix;
+        for (old_start, new_start, length) in sequence {
+            // cut the overlap with the previous segment in the old data
+            let overlap = old_end.saturating_sub(old_start);
+            if overlap >= length {
+                continue;
+            }
+            let (mut old_start, mut new_start, mut length) = (old_start + overlap, new_start + overlap, length - overlap);
+            // cut the overlap with the unchanged end
+            if old_start >= old_limit {
+                continue;
+            }
+            length = length.min(old_limit - old_start);
+            // extend the segment back into the changed part before it
+            while old_start > old_end && new_start > new_end && old_data[old_start - 1] == new_data[new_start - 1] {
+                old_start -= 1;
+                new_start -= 1;
+                length += 1;
+            }
+            segments.push((old_start, new_start, length));
+            old_end = old_start + length;
+            new_end = new_start + length;
+        }
+        if suffix > 0 {
+            segments.push((old_data.len() - suffix, new_data.len() - suffix, suffix));
+        }
+        // the matches which are out of order (moved data) are copied from the old data
+        Self::segments_to_differences(old_data.len(), new_data, &segments, &matches)
+    }
+
+    /// Matches of old blocks in the new data (old start, new start, length) ordered by the new start
+    fn block_matches(old_data: &[u8], new_data: &[u8], block_size: usize) -> Vec<Segment> {
+        let mut matches: Vec<Segment> = Vec::new();
+        if old_data.len() < block_size || new_data.len() < block_size {
+            return matches;
+        }
+        let mut blocks: HashMap<u32, Vec<usize>> = HashMap::new();
+        for start in (0..=(old_data.len() - block_size)).step_by(block_size) {
+            let hash = RollingHash::new(&old_data[start..(start + block_size)]);
+            blocks.entry(hash.value()).or_default().push(start);
+        }
+
+        let mut i = 0;
+        let mut new_end = 0;
+        let mut hash = RollingHash::new(&new_data[..block_size]);
+        while i + block_size <= new_data.len() {
+            let found = blocks.get(&hash.value()).and_then(|candidates| {
+                candidates
+                    .iter()
+                    .take(MAX_BLOCK_CANDIDATES)
+                    .find(|&&c| old_data[c..(c + block_size)] == new_data[i..(i + block_size)])
+            });
+            if let Some(&old_start) = found {
+                let mut length = block_size;
+                while old_start + length < old_data.len()
+                    && i + length < new_data.len()
+                    && old_data[old_start + length] == new_data[i + length]
+                {
+                    length += 1;
+                }
+                // extend the match back to the end of the previous match
+                let mut back = 0;
+                while back < old_start && i - back > new_end && old_data[old_start - back - 1] == new_data[i - back - 1] {
+                    back += 1;
+                }
+                matches.push((old_start - back, i - back, length + back));
+                i += length;
+                new_end = i;
+                if i + block_size <= new_data.len() {
+                    hash = RollingHash::new(&new_data[i..(i + block_size)]);
+                }
+                continue;
+            }
+            if i + block_size < new_data.len() {
+                hash.roll(new_data[i], new_data[i + block_size]);
+            }
+            i += 1;
+        }
+        matches
+    }
+
+    /// Differences for the changed parts between the common segments (ordered and not overlapping)
+    ///
+    ///
+    /// The copies (ordered by the new start and not overlapping) are used for the parts of the changes they cover,
+    /// every other changed byte is replaced or inserted.
+    fn segments_to_differences(old_len: usize, new_data: &[u8], segments: &[Segment], copies: &[Segment]) -> Vec<Difference> {
+        let mut differences: Vec<Difference> = Vec::new();
+        let mut old_pos = 0;
+        let mut new_pos = 0;
+        let mut copies = copies.iter().peekable();
+        // end marker to handle the changes after the last common segment
+        let end = (old_len, new_data.len(), 0);
+        for &(old_start, new_start, length) in segments.iter().chain(std::iter::once(&end)) {
+            // the old bytes of the change which are not replaced yet are always at the current position
+            let mut gap = Gap {
+                position: new_pos,
+                deleted: old_start - old_pos,
+            };
+            let mut literal_start = new_pos;
+            while let Some(&&(copy_old, copy_new, copy_length)) = copies.peek() {
+                if copy_new >= new_start {
+                    break;
+                }
+                let copy_end = (copy_new + copy_length).min(new_start);
+                let start = copy_new.max(new_pos);
+                if copy_end >= start + MIN_COPY_LENGTH {
+                    gap.literal(&mut differences, &new_data[literal_start..start]);
+                    gap.copy(&mut differences, copy_old + (start - copy_new), copy_end - start);
+                    literal_start = copy_end;
+                }
+                if copy_new + copy_length > new_start {
+                    // the rest of the copy can still be used after the common segment
+                    break;
+                }
+                copies.next();
+            }
+            gap.literal(&mut differences, &new_data[literal_start..new_start]);
+            if gap.deleted > 0 {
+                differences.push(Difference {
+                    action: DifferenceAction::Delete,
+                    range: Range::new(gap.position, gap.deleted),
+                    value: vec![],
+                    is_open: false,
+                });
+            }
+            old_pos = old_start + length;
+            new_pos = new_start + length;
+        }
+        differences
+    }
+
+    /// Common segments (old start, new start, length) of the longest common subsequence in order
+    pub(crate) fn common_segments(old_data: &[u8], new_data: &[u8]) -> Vec<Segment> {
+        let mut segments: Vec<Segment> = Vec::new();
+        Self::lcs_segments(old_data, new_data, 0, 0, &mut segments);
+        segments
+    }
+
+    /// Adds the common segments of old and new in order (offsets are added to the positions)
+    fn lcs_segments(old: &[u8], new: &[u8], old_offset: usize, new_offset: usize, segments: &mut Vec<Segment>) {
+        let prefix = old.iter().zip(new.iter()).take_while(|(a, b)| a == b).count();
+        if prefix > 0 {
+            segments.push((old_offset, new_offset, prefix));
+        }
+        let old = &old[prefix..];
+        let new = &new[prefix..];
+        let suffix = old.iter().rev().zip(new.iter().rev()).take_while(|(a, b)| a == b).count();
+        let old_mid = &old[..(old.len() - suffix)];
+        let new_mid = &new[..(new.len() - suffix)];
+
+        // with an empty side the rest is only an insert or delete
+        if !old_mid.is_empty() && !new_mid.is_empty() {
+            if let Some((x, y, u, v)) = Self::middle_snake(old_mid, new_mid) {
+                let old_mid_offset = old_offset + prefix;
+                let new_mid_offset = new_offset + prefix;
+                Self::lcs_segments(&old_mid[..x], &new_mid[..y], old_mid_offset, new_mid_offset, segments);
+                if u > x {
+                    segments.push((old_mid_offset + x, new_mid_offset + y, u - x));
+                }
+                Self::lcs_segments(&old_mid[u..], &new_mid[v..], old_mid_offset + u, new_mid_offset + v, segments);
+            }
+        }
+
+        if suffix > 0 {
+            segments.push((old_offset + prefix + old_mid.len(), new_offset + prefix + new_mid.len(), suffix));
+        }
+    }
+
+    /// Returns the middle snake (x, y) to (u, v) of the shortest edit script
+    ///
+    ///
+    /// Returns `None` if the edit distance is larger than [`MYERS_MAX_COST`]
+    fn middle_snake(old: &[u8], new: &[u8]) -> Option<(usize, usize, usize, usize)> {
+        let n = old.len() as isize;
+        let m = new.len() as isize;
+        let delta = n - m;
+        let odd = delta % 2 != 0;
+        let max = ((n + m + 1) / 2).min(MYERS_MAX_COST as isize);
+        let offset = max + 1;
+        // furthest reaching x of the forward and the reverse paths per diagonal
+        let mut forward = vec![0isize; (2 * offset + 1) as usize];
+        let mut reverse = vec![0isize; (2 * offset + 1) as usize];
+        let at = |k: isize| (k + offset) as usize;
+
+        for d in 0..=max {
+            for k in (-d..=d).step_by(2) {
+                let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                    forward[at(k + 1)]
+                } else {
+                    forward[at(k - 1)] + 1
+                };
+                let mut y = x - k;
+                let (x0, y0) = (x, y);
+                while x < n && y < m && old[x as usize] == new[y as usize] {
+                    x += 1;
+                    y += 1;
+                }
+                forward[at(k)] = x;
+                let reverse_k = delta - k;
+                if odd && reverse_k.abs() < d && x + reverse[at(reverse_k)] >= n {
+                    return Some((x0 as usize, y0 as usize, x as usize, y as usize));
+                }
+            }
+            for k in (-d..=d).step_by(2) {
+                let mut x = if k == -d || (k != d && reverse[at(k - 1)] < reverse[at(k + 1)]) {
+                    reverse[at(k + 1)]
+                } else {
+                    reverse[at(k - 1)] + 1
+                };
+                let mut y = x - k;
+                let (x0, y0) = (x, y);
+                while x < n && y < m && old[(n - 1 - x) as usize] == new[(m - 1 - y) as usize] {
+                    x += 1;
+                    y += 1;
+                }
+                reverse[at(k)] = x;
+                let forward_k = delta - k;
+                if !odd && forward_k.abs() <= d && x + forward[at(forward_k)] >= n {
+                    return Some(((n - x) as usize, (m - y) as usize, (n - x0) as usize, (m - y0) as usize));
+                }
+            }
+        }
+        None
+    }
+
+    /// Applies the differences in order to the data
+    ///
+    ///
+    /// The range start of every difference is the position in the data with all previous differences applied,
+    /// the source of a copy is always a position in the given data.<br/>
+    /// Returns [`SDDEError::RangeOutOfBounds`] (without an index) if a range does not fit into the data
+    pub fn apply_diff(data: &[u8], diff: &[Difference]) -> Result<Vec<u8>, SDDEError> {
+        let mut current = data.to_vec();
         for d in diff {
-            if d.action == DifferenceAction::Replace {
-                data[d.range.start..(d.range.start + d.range.length)].copy_from_slice(&d.value);
-            } else if d.action == DifferenceAction::Insert {
-                data.extend_from_slice(&d.value);
-            } else if d.action == DifferenceAction::Delete {
-                data.drain(d.range.start..(d.range.start + d.range.length));
+            Self::apply_difference(data, &mut current, d)?;
+        }
+        Ok(current)
+    }
+
+    /// Bytes which are removed or replaced by every difference when the differences are applied to the data
+    ///
+    ///
+    /// The bytes are stored in reversible patches, so the differences can be inverted (see [`Self::invert`]).
+    pub fn removed_bytes(data: &[u8], diff: &[Difference]) -> Result<Vec<Vec<u8>>, SDDEError> {
+        let mut current = data.to_vec();
+        let mut removed = Vec::with_capacity(diff.len());
+        for d in diff {
+            let end = d.range.start.saturating_add(d.range.length).min(current.len());
+            removed.push(match d.action {
+                DifferenceAction::Insert => Vec::new(),
+                _ => current[d.range.start.min(end)..end].to_vec(),
+            });
+            Self::apply_difference(data, &mut current, d)?;
+        }
+        Ok(removed)
+    }
+
+    /// Differences which undo the differences, the removed bytes are the bytes of [`Self::removed_bytes`]
+    pub fn invert(diff: &[Difference], removed: &[Vec<u8>]) -> Result<Vec<Difference>, SDDEError> {
+        if diff.len() != removed.len() {
+            return Err(SDDEError::DifferenceInvalid(format!(
+                "{} removed values for {} differences",
+                removed.len(),
+                diff.len()
+            )));
+        }
+        let mut inverted: Vec<Difference> = Vec::with_capacity(diff.len());
+        let mut push = |action: DifferenceAction, start: usize, value: Vec<u8>, length: usize| {
+            if length > 0 {
+                inverted.push(Difference {
+                    action,
+                    range: Range::new(start, length),
+                    value,
+                    is_open: false,
+                });
+            }
+        };
+        // the last difference is undone first, so every range is a position in the data at the time of the difference
+        for (d, old) in diff.iter().zip(removed).rev() {
+            let start = d.range.start;
+            match d.action {
+                DifferenceAction::Replace if old.len() == d.range.length => {
+                    push(DifferenceAction::Replace, start, old.clone(), old.len());
+                },
+                DifferenceAction::Replace => return Err(SDDEError::RangeOutOfBounds { index: None, range: d.range.clone() }),
+                DifferenceAction::Insert => push(DifferenceAction::Delete, start, Vec::new(), d.value.len()),
+                DifferenceAction::Delete => push(DifferenceAction::Insert, start, old.clone(), old.len()),
+                DifferenceAction::Copy { len, .. } if len == old.len() => {
+                    push(DifferenceAction::Replace, start, old.clone(), len);
+                },
+                DifferenceAction::Copy { len, .. } => {
+                    push(DifferenceAction::Delete, start, Vec::new(), len);
+                    push(DifferenceAction::Insert, start, old.clone(), old.len());
+                },
             }
         }
-        data
+        Ok(inverted)
     }
-}
\ No newline at end of file
+
+    /// Applies a single difference to the current data, the source of a copy is the data before the first difference
+    fn apply_difference(source: &[u8], data: &mut Vec<u8>, d: &Difference) -> Result<(), SDDEError> {
+        let out_of_bounds = || SDDEError::RangeOutOfBounds {
+            index: None,
+            range: d.range.clone(),
+        };
+        let end = d.range.start.checked_add(d.range.length).ok_or_else(out_of_bounds)?;
+        if d.action == DifferenceAction::Replace {
+            if end > data.len() || d.value.len() != d.range.length {
+                return Err(out_of_bounds());
+            }
+            data[d.range.start..end].copy_from_slice(&d.value);
+        } else if d.action == DifferenceAction::Insert {
+            if d.range.start > data.len() {
+                return Err(out_of_bounds());
+            }
+            data.splice(d.range.start..d.range.start, d.value.iter().copied());
+        } else if d.action == DifferenceAction::Delete {
+            if end > data.len() {
+                return Err(out_of_bounds());
+            }
+            data.drain(d.range.start..end);
+        } else if let DifferenceAction::Copy { src_start, len } = d.action {
+            let copied = src_start
+                .checked_add(len)
+                .filter(|src_end| *src_end <= source.len())
+                .map(|src_end| &source[src_start..src_end]);
+            match copied {
+                Some(copied) if end <= data.len() => {
+                    data.splice(d.range.start..end, copied.iter().copied());
+                },
+                _ => return Err(out_of_bounds()),
+            }
+        }
+        Ok(())
+    }
+}
@@ -103,7 +103,7 @@ mod test {
     #[test]
     fn from_bytes_replace_action() {
         let bytes = vec![b'r', b':', 4, b'-', 1, b'2'];
-        let difference = Difference::from_bytes(&bytes);
+        let difference = Difference::from_bytes(&bytes).unwrap();
         assert_eq!(difference.action, DifferenceAction::Replace);
         assert_eq!(difference.range, Range::new(4, 1));
         assert_eq!(difference.value, vec![b'2']);
@@ -113,7 +113,7 @@ mod test {
     #[test]
     fn from_bytes_insert_action() {
         let bytes = vec![b'i', b':', 4, b'-', 1, b'2'];
-        let difference = Difference::from_bytes(&bytes);
+        let difference = Difference::from_bytes(&bytes).unwrap();
         assert_eq!(difference.action, DifferenceAction::Insert);
         assert_eq!(difference.range, Range::new(4, 1));
         assert_eq!(difference.value, vec![b'2']);
@@ -122,7 +122,7 @@ mod test {
     #[test]
     fn from_bytes_delete_action() {
         let bytes = vec![b'd', b':', 4, b'-', 1];
-        let difference = Difference::from_bytes(&bytes);
+        let difference = Difference::from_bytes(&bytes).unwrap();
         assert_eq!(difference.action, DifferenceAction::Delete);
         assert_eq!(difference.range, Range::new(4, 1));
         assert!(difference.value.is_empty());
@@ -131,7 +131,7 @@ mod test {
     #[test]
     fn from_bytes_replace_action_with_same_inbetween() {
         let bytes = vec![b'r', b':', 4, b'-', 7, b'2', b'T', b'h', b'e', b'l', b'l', b'o'];
-        let difference = Difference::from_bytes(&bytes);
+        let difference = Difference::from_bytes(&bytes).unwrap();
         assert_eq!(difference.action, DifferenceAction::Replace);
         assert_eq!(difference.range, Range::new(4, 7));
         assert_eq!(difference.value, vec![b'2', b'T', b'h', b'e', b'l', b'l', b'o']);
@@ -140,7 +140,7 @@ mod test {
     #[test]
     fn from_bytes_large_data_sets() {
         let bytes = vec![b'r', b':', 9, b'-', 1, 11];
-        let difference = Difference::from_bytes(&bytes);
+        let difference = Difference::from_bytes(&bytes).unwrap();
         assert_eq!(difference.action, DifferenceAction::Replace);
         assert_eq!(difference.range, Range::new(9, 1));
         assert_eq!(difference.value, vec![11]);
@@ -149,13 +149,13 @@ mod test {
     #[test]
     fn from_bytes_multiple_actions() {
         let bytes1 = vec![b'r', b':', 2, b'-', 3, 6, 7, 8];
-        let difference1 = Difference::from_bytes(&bytes1);
+        let difference1 = Difference::from_bytes(&bytes1).unwrap();
         assert_eq!(difference1.action, DifferenceAction::Replace);
         assert_eq!(difference1.range, Range::new(2, 3));
         assert_eq!(difference1.value, vec![6, 7, 8]);
 
         let bytes2 = vec![b'i', b':', 5, b'-', 1, 9];
-        let difference2 = Difference::from_bytes(&bytes2);
+        let difference2 = Difference::from_bytes(&bytes2).unwrap();
         assert_eq!(difference2.action, DifferenceAction::Insert);
         assert_eq!(difference2.range, Range::new(5, 1));
         assert_eq!(difference2.value, vec![9]);
@@ -164,7 +164,7 @@ mod test {
     #[test]
     fn from_bytes_range_length_u16() {
         let bytes = [vec![b'r', b':', 0, b'-', b's', 1, 44], vec![3; 300]].concat();
-        let difference = Difference::from_bytes(&bytes);
+        let difference = Difference::from_bytes(&bytes).unwrap();
         assert_eq!(difference.action, DifferenceAction::Replace);
         assert_eq!(difference.range, Range::new(0, 300));
         assert_eq!(difference.value, vec![3; 300]);
@@ -173,7 +173,7 @@ mod test {
     #[test]
     fn from_bytes_range_length_u32() {
         let bytes = [vec![b'r', b':', 0, b'-', b'i', 0, 1, 0, 0], vec![3; 65_536]].concat();
-        let difference = Difference::from_bytes(&bytes);
+        let difference = Difference::from_bytes(&bytes).unwrap();
         assert_eq!(difference.action, DifferenceAction::Replace);
         assert_eq!(difference.range, Range::new(0, 65_536));
         assert_eq!(difference.value, vec![3; 65_536]);
@@ -183,7 +183,7 @@ mod test {
     #[ignore]
     fn from_bytes_range_length_u64() {
         let bytes = [vec![b'r', b':', 0, b'-', b'l', 0, 0, 0, 1, 0, 0, 0, 0], vec![3; 4_294_967_296]].concat();
-        let difference = Difference::from_bytes(&bytes);
+        let difference = Difference::from_bytes(&bytes).unwrap();
         assert_eq!(difference.action, DifferenceAction::Replace);
         assert_eq!(difference.range, Range::new(0, 4_294_967_296));
         assert_eq!(difference.value, vec![3; 4_294_967_296]);
@@ -195,7 +195,7 @@ mod test {
         let data_new = "Test2".as_bytes();
         let diff = DataDifference::diff(data_old, data_new);
         let data = data_old.to_owned();
-        let data = DataDifference::apply_diff(&data, &diff);
+        let data = DataDifference::apply_diff(&data, &diff).unwrap();
         assert_eq!(data, data_new);
     }
     
@@ -205,7 +205,7 @@ mod test {
         let data_new = "Test2".as_bytes();
         let diff = DataDifference::diff(data_old, data_new);
         let data = data_old.to_owned();
-        let data = DataDifference::apply_diff(&data, &diff);
+        let data = DataDifference::apply_diff(&data, &diff).unwrap();
         assert_eq!(data, data_new);
     }
     
@@ -215,7 +215,7 @@ mod test {
         let data_new = "Test".as_bytes();
         let diff = DataDifference::diff(data_old, data_new);
         let data = data_old.to_owned();
-        let data = DataDifference::apply_diff(&data, &diff);
+        let data = DataDifference::apply_diff(&data, &diff).unwrap();
         assert_eq!(data, data_new);
     }
         
@@ -225,7 +225,148 @@ mod test {
         let data_new = "TesNN".as_bytes();
         let diff = DataDifference::diff(data_old, data_new);
         let data = data_old.to_owned();
-        let data = DataDifference::apply_diff(&data, &diff);
+        let data = DataDifference::apply_diff(&data, &diff).unwrap();
         assert_eq!(data_new, data);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn apply_diff_insert_at_offset() {
+        let diff = vec![Difference {
+            action: DifferenceAction::Insert,
+            range: Range::new(2, 2),
+            value: "XY".as_bytes().to_vec(),
+            is_open: false,
+        }];
+        let data = DataDifference::apply_diff("Test".as_bytes(), &diff).unwrap();
+        assert_eq!(data, "TeXYst".as_bytes());
+    }
+
+    #[test]
+    fn apply_diff_copy_action() {
+        // the source is the old data even after the previous differences
+        let diff = vec![
+            Difference {
+                action: DifferenceAction::Delete,
+                range: Range::new(0, 4),
+                value: vec![],
+                is_open: false,
+            },
+            Difference {
+                action: DifferenceAction::Copy { src_start: 0, len: 4 },
+                range: Range::new(3, 1),
+                value: vec![],
+                is_open: false,
+            },
+        ];
+        let data = DataDifference::apply_diff("TestData".as_bytes(), &diff).unwrap();
+        assert_eq!(data, "DatTest".as_bytes());
+    }
+
+    #[test]
+    fn apply_diff_copy_out_of_bounds() {
+        let diff = vec![Difference {
+            action: DifferenceAction::Copy { src_start: 2, len: 4 },
+            range: Range::new(0, 0),
+            value: vec![],
+            is_open: false,
+        }];
+        let result = DataDifference::apply_diff("Test".as_bytes(), &diff);
+        assert!(matches!(result, Err(SDDEError::RangeOutOfBounds { index: None, .. })));
+    }
+
+    #[test]
+    fn similarity() {
+        assert_eq!(DataDifference::similarity(b"", b""), 1.0);
+        assert_eq!(DataDifference::similarity(b"Test", b"Test"), 1.0);
+        assert_eq!(DataDifference::similarity(b"Test", b""), 0.0);
+        assert_eq!(DataDifference::similarity(b"Test", b"TestTest"), 0.5);
+        assert_eq!(DataDifference::similarity(b"abcd", b"xbcx"), 0.5);
+    }
+
+    #[test]
+    fn diff_myers_insert_at_start() {
+        let data_old = vec![7; 100_000];
+        let data_new = [vec![1], data_old.clone()].concat();
+        let diff = DataDifference::diff_myers(&data_old, &data_new);
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].to_bytes(), vec![b'i', b':', 0, b'-', 1, 1]);
+        assert_eq!(DataDifference::apply_diff(&data_old, &diff).unwrap(), data_new);
+    }
+
+    #[test]
+    fn diff_myers_delete_in_middle() {
+        let data_old = "Hello big World".as_bytes();
+        let data_new = "Hello World".as_bytes();
+        let diff = DataDifference::diff_myers(data_old, data_new);
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].action, DifferenceAction::Delete);
+        assert_eq!(DataDifference::apply_diff(data_old, &diff).unwrap(), data_new);
+    }
+
+    #[test]
+    fn diff_myers_mixed_actions() {
+        let data_old = "The quick brown fox jumps over the lazy dog".as_bytes();
+        let data_new = "A quick red fox jumped over the dog!".as_bytes();
+        let diff = DataDifference::diff_myers(data_old, data_new);
+        assert_eq!(DataDifference::apply_diff(data_old, &diff).unwrap(), data_new);
+        let changed: usize = diff.iter().map(|d| d.range.length).sum();
+        assert!(changed < data_new.len());
+    }
+
+    #[test]
+    fn diff_myers_no_changes() {
+        let data = vec![1, 2, 3, 4, 5];
+        assert!(DataDifference::diff_myers(&data, &data).is_empty());
+    }
+
+    #[test]
+    fn diff_myers_empty_data() {
+        let data = vec![1, 2, 3];
+        let diff = DataDifference::diff_myers(&[], &data);
+        assert_eq!(DataDifference::apply_diff(&[], &diff).unwrap(), data);
+        let diff = DataDifference::diff_myers(&data, &[]);
+        assert!(DataDifference::apply_diff(&data, &diff).unwrap().is_empty());
+    }
+
+    #[test]
+    fn diff_myers_random_data() {
+        // xorshift to get reproducible data without a random crate
+        let mut state: u32 = 0x9e37_79b9;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+        for _ in 0..200 {
+            let old_len = (next() % 64) as usize;
+            let data_old: Vec<u8> = (0..old_len).map(|_| (next() % 4) as u8).collect();
+            let mut data_new = data_old.clone();
+            for _ in 0..(next() % 6) {
+                let pos = (next() as usize) % (data_new.len() + 1);
+                match next() % 3 {
+                    0 => data_new.insert(pos, (next() % 4) as u8),
+                    1 if pos < data_new.len() => { data_new.remove(pos); },
+                    _ if pos < data_new.len() => data_new[pos] = (next() % 4) as u8,
+                    _ => {}
+                }
+            }
+            let diff = DataDifference::diff_myers(&data_old, &data_new);
+            assert_eq!(DataDifference::apply_diff(&data_old, &diff).unwrap(), data_new);
+            let diff = DataDifference::diff(&data_old, &data_new);
+            assert_eq!(DataDifference::apply_diff(&data_old, &diff).unwrap(), data_new);
+        }
+    }
+
+    #[test]
+    fn diff_myers_text_files() {
+        let data_path = std::env::current_dir().expect("Failed to get current directory").join("test_files");
+        let files = ["text_1.txt", "text_2.txt", "text_3.txt"].map(|file| std::fs::read(data_path.join(file)).expect("Failed to read test file"));
+        for data_old in files.iter() {
+            for data_new in files.iter() {
+                let diff = DataDifference::diff_myers(data_old, data_new);
+                assert_eq!(&DataDifference::apply_diff(data_old, &diff).unwrap(), data_new);
+            }
+        }
+    }
+}
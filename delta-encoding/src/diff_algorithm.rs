@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use crate::data_difference::{DataDifference, Difference};
+use crate::SDDEError;
+
+/// Algorithm which creates the differences between old and new data and applies them
+///
+///
+/// The id is stored in the patch header, so the receiver can apply the patch with the same algorithm.
+/// Custom algorithms should use ids from 128 upwards, the ids below are reserved for the built-in algorithms.
+pub trait DiffAlgorithm: Send + Sync {
+    fn id(&self) -> u8;
+
+    fn diff(&self, old_data: &[u8], new_data: &[u8]) -> Vec<Difference>;
+
+    fn apply(&self, data: &[u8], diffs: &[Difference]) -> Result<Vec<u8>, SDDEError> {
+        DataDifference::apply_diff(data, diffs)
+    }
+}
+
+/// Compares the bytes at the same position, fast but every shift of the data is a replace until the end
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PositionalDiff {}
+
+impl PositionalDiff {
+    pub const ID: u8 = 0;
+}
+
+impl DiffAlgorithm for PositionalDiff {
+    fn id(&self) -> u8 {
+        Self::ID
+    }
+
+    fn diff(&self, old_data: &[u8], new_data: &[u8]) -> Vec<Difference> {
+        DataDifference::diff(old_data, new_data)
+    }
+}
+
+/// Myers O(ND) diff (longest common subsequence) with inserts and deletes anywhere in the data
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LcsDiff {}
+
+impl LcsDiff {
+    pub const ID: u8 = 1;
+}
+
+impl DiffAlgorithm for LcsDiff {
+    fn id(&self) -> u8 {
+        Self::ID
+    }
+
+    fn diff(&self, old_data: &[u8], new_data: &[u8]) -> Vec<Difference> {
+        DataDifference::diff_myers(old_data, new_data)
+    }
+}
+
+/// Rolling hash diff which finds blocks of the old data in the new data, useful for large data
+#[derive(Debug, Clone, Copy)]
+pub struct BlockDiff {
+    pub block_size: usize,
+}
+
+impl BlockDiff {
+    pub const ID: u8 = 2;
+
+    pub fn new(block_size: usize) -> BlockDiff {
+        BlockDiff { block_size }
+    }
+}
+
+impl Default for BlockDiff {
+    fn default() -> Self {
+        BlockDiff { block_size: 16 }
+    }
+}
+
+impl DiffAlgorithm for BlockDiff {
+    fn id(&self) -> u8 {
+        Self::ID
+    }
+
+    fn diff(&self, old_data: &[u8], new_data: &[u8]) -> Vec<Difference> {
+        DataDifference::diff_blocks(old_data, new_data, self.block_size)
+    }
+}
+
+/// Algorithm which is selected at runtime (e.g. by its name)
+impl<T: DiffAlgorithm + ?Sized> DiffAlgorithm for Box<T> {
+    fn id(&self) -> u8 {
+        (**self).id()
+    }
+
+    fn diff(&self, old_data: &[u8], new_data: &[u8]) -> Vec<Difference> {
+        (**self).diff(old_data, new_data)
+    }
+
+    fn apply(&self, data: &[u8], diffs: &[Difference]) -> Result<Vec<u8>, SDDEError> {
+        (**self).apply(data, diffs)
+    }
+}
+
+/// Built-in algorithm for the id of a patch header
+pub fn diff_algorithm_from_id(id: u8) -> Option<Arc<dyn DiffAlgorithm>> {
+    match id {
+        PositionalDiff::ID => Some(Arc::new(PositionalDiff::default())),
+        LcsDiff::ID => Some(Arc::new(LcsDiff::default())),
+        BlockDiff::ID => Some(Arc::new(BlockDiff::default())),
+        _ => None,
+    }
+}
@@ -0,0 +1,222 @@
+#[cfg(test)]
+mod diff_algorithm {
+    use std::collections::BTreeMap;
+
+    use crate::*;
+
+    fn algorithms() -> Vec<Box<dyn DiffAlgorithm>> {
+        vec![
+            Box::new(PositionalDiff::default()),
+            Box::new(LcsDiff::default()),
+            Box::new(BlockDiff::default()),
+            Box::new(BlockDiff::new(4)),
+        ]
+    }
+
+    fn test_files() -> Vec<Vec<u8>> {
+        let data_path = std::env::current_dir().expect("Failed to get current directory").join("test_files");
+        ["text_1.txt", "text_2.txt", "text_3.txt"]
+            .iter()
+            .map(|file| std::fs::read(data_path.join(file)).expect("Failed to read test file"))
+            .collect()
+    }
+
+    #[test]
+    fn diff_apply_text_files() {
+        let files = test_files();
+        for algorithm in algorithms() {
+            for data_old in files.iter() {
+                for data_new in files.iter() {
+                    let diff = algorithm.diff(data_old, data_new);
+                    assert_eq!(&algorithm.apply(data_old, &diff).unwrap(), data_new);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn diff_apply_small_data() {
+        let samples: Vec<(&[u8], &[u8])> = vec![
+            (b"", b""),
+            (b"", b"Test"),
+            (b"Test", b""),
+            (b"Test", b"Test2"),
+            (b"Test2", b"Test"),
+            (b"Test", b"2Test"),
+            (b"abcdefghijklmnopqrstuvwxyz", b"abcdefghijkl0123mnopqrstuvwxyz"),
+            (b"abcdefghijklmnopqrstuvwxyz", b"abcdefqrstuvwxyz"),
+        ];
+        for algorithm in algorithms() {
+            for (data_old, data_new) in samples.iter() {
+                let diff = algorithm.diff(data_old, data_new);
+                assert_eq!(&algorithm.apply(data_old, &diff).unwrap(), data_new);
+            }
+        }
+    }
+
+    /// Reproducible data without repeating blocks
+    fn random_data(length: usize) -> Vec<u8> {
+        let mut state: u32 = 0x2545_f491;
+        (0..length)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn block_diff_insert_at_start() {
+        let data_old = random_data(100_000);
+        let data_new = [vec![1, 2, 3], data_old.clone()].concat();
+        let diff = BlockDiff::default().diff(&data_old, &data_new);
+        let changed: usize = diff.iter().map(|d| d.range.length).sum();
+        assert!(changed <= 16, "{:?}", diff);
+        assert_eq!(DataDifference::apply_diff(&data_old, &diff).unwrap(), data_new);
+    }
+
+    #[test]
+    fn block_diff_delete_in_middle() {
+        let data_old = random_data(10_000);
+        let data_new = [&data_old[..3_000], &data_old[6_000..]].concat();
+        let diff = BlockDiff::default().diff(&data_old, &data_new);
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].action, DifferenceAction::Delete);
+        assert_eq!(diff[0].range, Range::new(3_000, 3_000));
+    }
+
+    #[test]
+    fn block_diff_moved_block() {
+        // the moved block is inserted again but the rest of the data is kept
+        let data_old = random_data(10_000);
+        let data_new = [&data_old[8_000..], &data_old[..8_000]].concat();
+        let diff = BlockDiff::default().diff(&data_old, &data_new);
+        let changed: usize = diff.iter().map(|d| d.range.length).sum();
+        assert!(changed <= 4_000, "{:?}", diff);
+        assert_eq!(DataDifference::apply_diff(&data_old, &diff).unwrap(), data_new);
+    }
+
+    #[test]
+    fn block_diff_copies_moved_block() {
+        let data_old = random_data(10_000);
+        let data_new = [&data_old[5_000..], &data_old[..5_000]].concat();
+        let diff = BlockDiff::default().diff(&data_old, &data_new);
+        let values: usize = diff.iter().map(|d| d.value.len()).sum();
+        assert_eq!(values, 0, "{:?}", diff);
+        assert!(diff.iter().any(|d| matches!(d.action, DifferenceAction::Copy { .. })));
+        assert_eq!(DataDifference::apply_diff(&data_old, &diff).unwrap(), data_new);
+    }
+
+    #[test]
+    fn block_diff_copies_duplicated_block() {
+        let data_old = random_data(4_000);
+        let data_new = [&data_old[..3_000], &data_old[1_000..2_000], &data_old[3_000..]].concat();
+        let diff = BlockDiff::default().diff(&data_old, &data_new);
+        let encoded: usize = diff.iter().map(|d| d.encode().len()).sum();
+        assert!(encoded < 32, "{:?}", diff);
+        assert_eq!(DataDifference::apply_diff(&data_old, &diff).unwrap(), data_new);
+    }
+
+    #[test]
+    fn block_diff_copies_in_patch() {
+        let data_old = random_data(2_000);
+        let data_new = [&data_old[1_000..], "changed".as_bytes(), &data_old[..1_000]].concat();
+        let props = vec![IndexedData::new(0, data_old)];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        sdd.set_diff_algorithm(BlockDiff::default());
+        let diff_data = sdd.patch(&[IndexedData::new(0, data_new.clone())]);
+        assert!(diff_data.len() < 100, "{}", diff_data.len());
+
+        let mut sdd2 = SimpleDirectDeltaEncoding::new(&props);
+        assert!(sdd2.apply_patch(&diff_data).is_ok());
+        assert_eq!(sdd2.data_collection.get(&0).unwrap().data, data_new);
+    }
+
+    #[test]
+    fn block_diff_short_data() {
+        // shorter than a block
+        assert!(BlockDiff::default().diff(b"short", b"short").is_empty());
+        let diff = BlockDiff::default().diff(b"short", b"shirt");
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].range, Range::new(2, 1));
+        assert_eq!(DataDifference::apply_diff(b"short", &diff).unwrap(), b"shirt");
+    }
+
+    #[test]
+    fn block_diff_unchanged_short_entry() {
+        let props = vec![IndexedData::new(0, "short".as_bytes().to_vec()), IndexedData::new(1, random_data(100))];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        sdd.set_diff_algorithm(BlockDiff::default());
+        let new_data = vec![IndexedData::new(0, "short".as_bytes().to_vec()), IndexedData::new(1, random_data(200))];
+        let diff_data = sdd.patch(&new_data);
+        let inspector = PatchInspector::new(&diff_data).unwrap();
+        assert!(inspector.records.iter().all(|r| r.index == 1), "{:?}", inspector.records);
+    }
+
+    #[test]
+    fn algorithm_from_id() {
+        for algorithm in algorithms() {
+            assert_eq!(diff_algorithm_from_id(algorithm.id()).unwrap().id(), algorithm.id());
+        }
+        assert!(diff_algorithm_from_id(200).is_none());
+    }
+
+    #[test]
+    fn apply_patch_with_algorithm_from_header() {
+        let files = test_files();
+        let props = vec![IndexedData::new(0, files[0].clone()), IndexedData::new(1, files[1].clone())];
+        let new_data = vec![IndexedData::new(0, files[2].clone()), IndexedData::new(1, files[0].clone())];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        sdd.set_diff_algorithm(BlockDiff::default());
+        let diff_data = sdd.patch(&new_data);
+        assert_eq!(PatchHeader::from_bytes(&diff_data).unwrap().0.diff_algorithm, BlockDiff::ID);
+
+        // the receiver uses the positional algorithm
+        let mut sdd2 = SimpleDirectDeltaEncoding::new(&props);
+        assert!(sdd2.apply_patch(&diff_data).is_ok());
+        assert_eq!(fold(&sdd.data_collection), fold(&sdd2.data_collection));
+    }
+
+    struct ReverseDiff {}
+
+    /// Custom algorithm which stores the new data reversed
+    impl DiffAlgorithm for ReverseDiff {
+        fn id(&self) -> u8 {
+            200
+        }
+
+        fn diff(&self, old_data: &[u8], new_data: &[u8]) -> Vec<Difference> {
+            let reversed: Vec<u8> = new_data.iter().rev().copied().collect();
+            DataDifference::diff(old_data, &reversed)
+        }
+
+        fn apply(&self, data: &[u8], diffs: &[Difference]) -> Result<Vec<u8>, SDDEError> {
+            let mut data = DataDifference::apply_diff(data, diffs)?;
+            data.reverse();
+            Ok(data)
+        }
+    }
+
+    #[test]
+    fn apply_patch_with_custom_algorithm() {
+        let props = vec![IndexedData::new(0, Vec::new())];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        sdd.set_diff_algorithm(ReverseDiff {});
+        let diff_data = sdd.patch(&[IndexedData::new(0, "Test".as_bytes().to_vec())]);
+
+        let mut sdd2 = SimpleDirectDeltaEncoding::new(&props);
+        let result = sdd2.apply_patch(&diff_data);
+        assert!(matches!(result, Err(SDDEError::UnknownDiffAlgorithm { id: 200 })));
+        assert!(sdd2.data_collection.get(&0).unwrap().data.is_empty());
+
+        sdd2.set_diff_algorithm(ReverseDiff {});
+        assert!(sdd2.apply_patch(&diff_data).is_ok());
+        assert_eq!(sdd2.data_collection.get(&0).unwrap().data, "Test".as_bytes());
+    }
+
+    fn fold(data_collection: &BTreeMap<Index, IndexedData>) -> Vec<u8> {
+        SimpleDirectDeltaEncoding::fold_index(&data_collection.values().cloned().collect::<Vec<_>>())
+    }
+}
@@ -0,0 +1,128 @@
+use crate::checksum::ChecksumAlgorithm;
+use crate::diff_algorithm::DiffAlgorithm;
+use crate::{Index, IndexedData, SDDEError, SimpleDirectDeltaEncoding};
+
+/// Id of a state in the [`PatchHistory`], the initial state is revision 0 and every patch adds one
+pub type Revision = u64;
+
+/// Log of the patches of a [`SimpleDirectDeltaEncoding`] with revision ids
+///
+///
+/// The history keeps the initial state and every patch, so a receiver at any past revision can be brought to the head revision.<br/>
+/// The patch from a past revision is created by replaying the patches up to that revision and patching the result with the head data.
+#[derive(Clone)]
+pub struct PatchHistory {
+    base: SimpleDirectDeltaEncoding,
+    encoding: SimpleDirectDeltaEncoding,
+    patches: Vec<Vec<u8>>,
+}
+
+impl PatchHistory {
+    pub fn new(data: &[IndexedData]) -> PatchHistory {
+        Self::from_encoding(SimpleDirectDeltaEncoding::new(data))
+    }
+
+    /// The current state of the encoding is revision 0 (pending index mapping changes are part of the first patch)
+    pub fn from_encoding(encoding: SimpleDirectDeltaEncoding) -> PatchHistory {
+        let mut base = encoding.clone();
+        base.index_mapping.clear();
+        PatchHistory {
+            base,
+            encoding,
+            patches: Vec::new(),
+        }
+    }
+
+    /// Encoding with the data of the head revision
+    pub fn encoding(&self) -> &SimpleDirectDeltaEncoding {
+        &self.encoding
+    }
+
+    /// Revision of the last patch (0 if nothing was patched yet)
+    pub fn head(&self) -> Revision {
+        self.patches.len() as Revision
+    }
+
+    /// Change the index mapping for the next patch
+    pub fn change_index_mapping(&mut self, index: Index, key: &[u8]) {
+        self.encoding.change_index_mapping(index, key);
+    }
+
+    /// Remove the index mapping with the next patch
+    pub fn remove_index_mapping(&mut self, index: Index) {
+        self.encoding.remove_index_mapping(index);
+    }
+
+    /// Change the algorithm used to create the next patches (also used for the patches from past revisions)
+    pub fn set_diff_algorithm(&mut self, diff_algorithm: impl DiffAlgorithm + 'static) {
+        self.encoding.set_diff_algorithm(diff_algorithm);
+    }
+
+    /// Change the algorithm of the CRC values of the next patches
+    pub fn set_checksum_algorithm(&mut self, checksum_algorithm: ChecksumAlgorithm) {
+        self.encoding.set_checksum_algorithm(checksum_algorithm);
+    }
+
+    /// Patch the data with the new data, store the patch and return the new revision with the diff data
+    pub fn patch(&mut self, new_data: &[IndexedData]) -> (Revision, Vec<u8>) {
+        let diff_data = self.encoding.patch(new_data);
+        self.patches.push(diff_data.clone());
+        (self.head(), diff_data)
+    }
+
+    /// Diff data of the patch which created the revision
+    pub fn get_patch(&self, revision: Revision) -> Option<&[u8]> {
+        let position = revision.checked_sub(1)?;
+        self.patches.get(usize::try_from(position).ok()?).map(|x| x.as_slice())
+    }
+
+    /// Encoding with the data and index mappings of the revision
+    pub fn state_at(&self, revision: Revision) -> Result<SimpleDirectDeltaEncoding, SDDEError> {
+        if revision > self.head() {
+            return Err(SDDEError::UnknownRevision { revision });
+        }
+        let mut encoding = self.base.clone();
+        // custom algorithms of the sender can be replayed
+        encoding.diff_algorithm = self.encoding.diff_algorithm.clone();
+        for diff_data in self.patches.iter().take(revision as usize) {
+            encoding.apply_patch(diff_data)?;
+        }
+        Ok(encoding)
+    }
+
+    /// Diff data of a single patch from the revision to the head revision
+    ///
+    ///
+    /// The patch is created with the current diff and checksum algorithm and can be applied by a receiver with the data of the revision.
+    pub fn patch_since(&self, revision: Revision) -> Result<Vec<u8>, SDDEError> {
+        let mut encoding = self.state_at(revision)?;
+        encoding.set_checksum_algorithm(self.encoding.checksum_algorithm);
+
+        let head_mapping = &self.encoding.last_index_mapping;
+        for (index, value) in head_mapping.iter() {
+            if encoding.last_index_mapping.get(index).map(|x| &x.current) != Some(&value.current) {
+                encoding.change_index_mapping(*index, &value.current);
+            }
+        }
+        let removed_mappings: Vec<Index> = encoding
+            .last_index_mapping
+            .keys()
+            .filter(|index| !head_mapping.contains_key(index))
+            .copied()
+            .collect();
+        for index in removed_mappings {
+            encoding.remove_index_mapping(index);
+        }
+
+        let head_data: Vec<IndexedData> = self.encoding.data_collection.values().cloned().collect();
+        let diff_data = encoding.patch(&head_data);
+        // e.g. the mapping of an index which is removed in the same patch is not part of the patch
+        if encoding.crc != self.encoding.crc {
+            return Err(SDDEError::TargetMismatch {
+                expected: self.encoding.crc.clone(),
+                actual: encoding.crc,
+            });
+        }
+        Ok(diff_data)
+    }
+}
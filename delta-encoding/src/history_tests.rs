@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod history {
+    use crate::*;
+
+    fn revisions() -> Vec<Vec<IndexedData>> {
+        vec![
+            vec![
+                IndexedData::new(0, "Test2".as_bytes().to_vec()),
+                IndexedData::new(1, "Test 1".as_bytes().to_vec()),
+            ],
+            vec![
+                IndexedData::new(0, "Test2".as_bytes().to_vec()),
+                IndexedData::new(2, "Test 1".as_bytes().to_vec()),
+            ],
+            vec![
+                IndexedData::new(0, "Test 3".as_bytes().to_vec()),
+                IndexedData::new(2, "Test 1".as_bytes().to_vec()),
+                IndexedData::new(3, "new".as_bytes().to_vec()),
+            ],
+        ]
+    }
+
+    fn history() -> PatchHistory {
+        let mut history = PatchHistory::new(&[
+            IndexedData::new(0, "Test".as_bytes().to_vec()),
+            IndexedData::new(1, "Test 1".as_bytes().to_vec()),
+        ]);
+        for (revision, new_data) in revisions().iter().enumerate() {
+            history.change_index_mapping(new_data.last().unwrap().index, format!("key {}", revision).as_bytes());
+            let (patch_revision, _) = history.patch(new_data);
+            assert_eq!(patch_revision, revision as Revision + 1);
+        }
+        history
+    }
+
+    #[test]
+    fn revisions_are_increasing() {
+        let history = history();
+        assert_eq!(history.head(), 3);
+        assert!(history.get_patch(0).is_none());
+        assert!(history.get_patch(3).is_some());
+        assert!(history.get_patch(4).is_none());
+    }
+
+    #[test]
+    fn patch_since_every_revision() {
+        let history = history();
+        for revision in 0..=history.head() {
+            // the receiver has the state of the revision by applying the stored patches
+            let mut receiver = SimpleDirectDeltaEncoding::new(&[
+                IndexedData::new(0, "Test".as_bytes().to_vec()),
+                IndexedData::new(1, "Test 1".as_bytes().to_vec()),
+            ]);
+            for patch_revision in 1..=revision {
+                assert!(receiver.apply_patch(history.get_patch(patch_revision).unwrap()).is_ok());
+            }
+            let diff_data = history.patch_since(revision).unwrap();
+            let result = receiver.apply_patch(&diff_data);
+            assert!(result.is_ok(), "revision {} {:?}", revision, result);
+            assert_eq!(receiver.get_state(), history.encoding().get_state());
+            assert_eq!(receiver.crc, history.encoding().crc);
+        }
+    }
+
+    #[test]
+    fn state_at_revision() {
+        let history = history();
+        let state = history.state_at(1).unwrap();
+        assert_eq!(state.data_collection.get(&0).unwrap().data, "Test2".as_bytes());
+        assert_eq!(state.get_index_mapping().get(&1).unwrap().current, "key 0".as_bytes());
+        assert!(history.state_at(0).unwrap().get_index_mapping().is_empty());
+    }
+
+    #[test]
+    fn patch_since_unknown_revision() {
+        let history = history();
+        assert!(matches!(
+            history.patch_since(4),
+            Err(SDDEError::UnknownRevision { revision: 4 })
+        ));
+    }
+
+    #[test]
+    fn patch_since_with_checksum_algorithm() {
+        let mut history = history();
+        history.set_checksum_algorithm(ChecksumAlgorithm::Blake3);
+        history.set_diff_algorithm(LcsDiff::default());
+        history.patch(&[IndexedData::new(0, "Test 4".as_bytes().to_vec())]);
+
+        let mut receiver = SimpleDirectDeltaEncoding::new(&[
+            IndexedData::new(0, "Test".as_bytes().to_vec()),
+            IndexedData::new(1, "Test 1".as_bytes().to_vec()),
+        ]);
+        assert!(receiver.apply_patch(&history.patch_since(0).unwrap()).is_ok());
+        assert_eq!(receiver.get_state(), history.encoding().get_state());
+    }
+}
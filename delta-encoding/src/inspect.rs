@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::data_difference::{Difference, DifferenceAction};
+use crate::patch_format::{
+    ByteCursor, PatchHeader, PatchSource, RECORD_COPY_ENTRY, RECORD_ENTRY, RECORD_MAP_NAME, RECORD_OLD_ENTRY, RECORD_OLD_MAP_NAME,
+    RECORD_REMOVE_ENTRY, RECORD_REMOVE_MAP_NAME,
+};
+use crate::{EntryDifference, Index, RemovedValue, SDDEError, SimpleDirectDeltaEncoding};
+
+/// Decoded patch with the header and every record in the order of the patch, e.g. to debug a patch
+///
+///
+/// The [`fmt::Display`] output is similar to a unified diff, every difference is a hunk with the range in the data of the index.<br/>
+/// UTF-8 values are shown as text lines, other values as hex bytes.
+/// The removed bytes are only known for reversible patches, otherwise a removed range is shown with its length.
+///
+/// Version 0 patches are decoded like [`SimpleDirectDeltaEncoding::get_differences`], their records are ordered by the index and have no offsets.
+#[derive(Debug, Clone)]
+pub struct PatchInspector {
+    pub header: PatchHeader,
+    /// Bytes count of the header
+    pub header_size: usize,
+    /// Bytes count of the whole patch
+    pub size: usize,
+    pub records: Vec<InspectedRecord>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InspectedRecord {
+    pub index: Index,
+    /// Position of the control byte of the record in the patch (None for version 0 patches)
+    pub offset: Option<usize>,
+    /// Bytes count of the record (None for version 0 patches)
+    pub size: Option<usize>,
+    pub operation: RecordOperation,
+}
+
+/// Payload of a record, see [`SimpleDirectDeltaEncoding::patch`] for the binary format
+#[derive(Debug, Clone)]
+pub enum RecordOperation {
+    Entry { diffs: Vec<Difference> },
+    CopyEntry { source_index: Index, diffs: Vec<Difference> },
+    RemoveEntry,
+    MapName { diffs: Vec<Difference> },
+    RemoveMapName,
+    OldEntry(RemovedValue),
+    OldMapName(RemovedValue),
+}
+
+impl RecordOperation {
+    /// Operation of a single record which is read into an empty entry
+    fn new(tag: u8, entry: EntryDifference) -> Option<RecordOperation> {
+        Some(match tag {
+            RECORD_ENTRY => RecordOperation::Entry { diffs: entry.diffs },
+            RECORD_COPY_ENTRY => RecordOperation::CopyEntry {
+                source_index: entry.copy_from?,
+                diffs: entry.diffs,
+            },
+            RECORD_REMOVE_ENTRY => RecordOperation::RemoveEntry,
+            RECORD_MAP_NAME => RecordOperation::MapName {
+                diffs: entry.map_name_changed?,
+            },
+            RECORD_REMOVE_MAP_NAME => RecordOperation::RemoveMapName,
+            RECORD_OLD_ENTRY => RecordOperation::OldEntry(entry.old_data?),
+            RECORD_OLD_MAP_NAME => RecordOperation::OldMapName(entry.old_map_name?),
+            _ => return None,
+        })
+    }
+}
+
+impl PatchInspector {
+    pub fn new(patch: &[u8]) -> Result<PatchInspector, SDDEError> {
+        let mut cursor = ByteCursor::new(patch);
+        let header = PatchHeader::read(&mut cursor)?;
+        let header_size = cursor.offset();
+        if header.version == 0 {
+            return Ok(PatchInspector {
+                header,
+                header_size,
+                size: patch.len(),
+                records: Self::legacy_records(SimpleDirectDeltaEncoding::on_get_legacy_differences(&mut cursor)?),
+            });
+        }
+
+        let mut records: Vec<InspectedRecord> = Vec::new();
+        while !cursor.is_empty() {
+            let offset = cursor.offset();
+            // the control byte is validated before the index and payload are read
+            let (tag, index) = SimpleDirectDeltaEncoding::read_record_start(&mut cursor)?;
+            let mut entry = EntryDifference::default();
+            SimpleDirectDeltaEncoding::read_record(&mut cursor, tag, &mut entry)?;
+            let operation = RecordOperation::new(tag, entry).ok_or(SDDEError::UnknownControlByte { byte: tag, offset })?;
+            records.push(InspectedRecord {
+                index,
+                offset: Some(offset),
+                size: Some(cursor.offset() - offset),
+                operation,
+            });
+        }
+
+        Ok(PatchInspector {
+            header,
+            header_size,
+            size: patch.len(),
+            records,
+        })
+    }
+
+    /// Records of the differences of a version 0 patch (only data entries, removed entries and map names)
+    fn legacy_records(diffs: BTreeMap<Index, EntryDifference>) -> Vec<InspectedRecord> {
+        let mut records: Vec<InspectedRecord> = Vec::new();
+        for (index, entry) in diffs {
+            let mut operations: Vec<RecordOperation> = Vec::new();
+            if entry.remove_entry {
+                operations.push(RecordOperation::RemoveEntry);
+            } else if entry.data_changed && !(entry.diffs.is_empty() && entry.map_name_changed.is_some()) {
+                // every index of a version 0 patch is a data entry, also if only the map name changes
+                operations.push(RecordOperation::Entry { diffs: entry.diffs });
+            }
+            if let Some(diffs) = entry.map_name_changed {
+                operations.push(RecordOperation::MapName { diffs });
+            }
+            records.extend(operations.into_iter().map(|operation| InspectedRecord {
+                index,
+                offset: None,
+                size: None,
+                operation,
+            }));
+        }
+        records
+    }
+
+    /// Removed bytes of the differences of an entry or map name record (only in reversible patches)
+    fn removed_values(&self, index: Index, map_name: bool) -> Option<&RemovedValue> {
+        self.records
+            .iter()
+            .filter(|x| x.index == index)
+            .find_map(|record| match &record.operation {
+                RecordOperation::OldEntry(removed) if !map_name => Some(removed),
+                RecordOperation::OldMapName(removed) if map_name => Some(removed),
+                _ => None,
+            })
+    }
+}
+
+impl fmt::Display for PatchInspector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "patch version {}, diff algorithm {}, checksum algorithm {}, {} bytes (header {} bytes)",
+            self.header.version, self.header.diff_algorithm, self.header.checksum_algorithm, self.size, self.header_size
+        )?;
+        let target_crc = if self.header.target_crc.is_empty() {
+            "unverified".to_owned()
+        } else {
+            to_hex(&self.header.target_crc)
+        };
+        writeln!(f, "crc {} -> {}", to_hex(&self.header.crc), target_crc)?;
+
+        for record in self.records.iter() {
+            let (name, diffs, map_name) = match &record.operation {
+                RecordOperation::Entry { diffs } => ("entry".to_owned(), Some(diffs), false),
+                RecordOperation::CopyEntry { source_index, diffs } => {
+                    (format!("copy of index {}", source_index), Some(diffs), false)
+                },
+                RecordOperation::RemoveEntry => ("remove entry".to_owned(), None, false),
+                RecordOperation::MapName { diffs } => ("map name".to_owned(), Some(diffs), true),
+                RecordOperation::RemoveMapName => ("remove map name".to_owned(), None, true),
+                RecordOperation::OldEntry(removed) => (format!("old entry, {} values", removed.values.len()), None, false),
+                RecordOperation::OldMapName(removed) => {
+                    (format!("old map name, {} values", removed.values.len()), None, true)
+                },
+            };
+            match (record.offset, record.size) {
+                (Some(offset), Some(size)) => {
+                    writeln!(f, "=== index {} {} (offset {}, {} bytes)", record.index, name, offset, size)?
+                },
+                _ => writeln!(f, "=== index {} {}", record.index, name)?,
+            }
+            let Some(diffs) = diffs else {
+                continue;
+            };
+            let removed = self
+                .removed_values(record.index, map_name)
+                .filter(|x| x.values.len() == diffs.len());
+            for (position, diff) in diffs.iter().enumerate() {
+                write_hunk(f, diff, removed.map(|x| x.values[position].as_slice()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Hunk header with the old and new range of the difference followed by the removed and inserted lines
+fn write_hunk(f: &mut fmt::Formatter<'_>, diff: &Difference, removed: Option<&[u8]>) -> fmt::Result {
+    let start = diff.range.start;
+    let (old_length, new_length, action) = match diff.action {
+        DifferenceAction::Replace => (diff.range.length, diff.range.length, "replace".to_owned()),
+        DifferenceAction::Insert => (0, diff.range.length, "insert".to_owned()),
+        DifferenceAction::Delete => (diff.range.length, 0, "delete".to_owned()),
+        DifferenceAction::Copy { src_start, len } => (diff.range.length, len, format!("copy {}..{}", src_start, src_start + len)),
+    };
+    writeln!(f, "@@ -{},{} +{},{} @@ {}", start, old_length, start, new_length, action)?;
+    if old_length > 0 {
+        match removed {
+            Some(removed) => write_lines(f, '-', removed)?,
+            None => writeln!(f, "-[{} bytes]", old_length)?,
+        }
+    }
+    if diff.action.has_value() && new_length > 0 {
+        write_lines(f, '+', &diff.value)?;
+    }
+    Ok(())
+}
+
+fn write_lines(f: &mut fmt::Formatter<'_>, prefix: char, value: &[u8]) -> fmt::Result {
+    match std::str::from_utf8(value) {
+        Ok(text) => {
+            for line in text.strip_suffix('\n').unwrap_or(text).split('\n') {
+                writeln!(f, "{}{}", prefix, line)?;
+            }
+            Ok(())
+        },
+        Err(_) => writeln!(f, "{}[{}]", prefix, to_hex(value)),
+    }
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("{:02x}", x)).collect()
+}
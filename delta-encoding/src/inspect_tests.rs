@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod inspect {
+    use crate::*;
+
+    fn sender() -> SimpleDirectDeltaEncoding {
+        let mut sender = SimpleDirectDeltaEncoding::new(&[
+            IndexedData::new(0, "Hello World".as_bytes().to_vec()),
+            IndexedData::new(1, "Test 1".as_bytes().to_vec()),
+            IndexedData::new(2, vec![0, 159, 146, 150]),
+        ]);
+        sender.set_diff_algorithm(LcsDiff::default());
+        sender.change_index_mapping(0, "greeting".as_bytes());
+        sender
+    }
+
+    fn new_data() -> Vec<IndexedData> {
+        vec![
+            IndexedData::new(0, "Hello brave\nnew World".as_bytes().to_vec()),
+            IndexedData::new(2, vec![0, 159, 146, 151]),
+            IndexedData::new(3, "Test 1".as_bytes().to_vec()),
+        ]
+    }
+
+    #[test]
+    fn inspect_records() {
+        let patch = sender().patch(&new_data());
+        let inspector = PatchInspector::new(&patch).unwrap();
+        assert_eq!(inspector.header, PatchHeader::from_bytes(&patch).unwrap().0);
+        assert_eq!(inspector.size, patch.len());
+        // the records cover the whole patch
+        let mut offset = inspector.header_size;
+        for record in inspector.records.iter() {
+            assert_eq!(record.offset, Some(offset));
+            offset += record.size.unwrap();
+        }
+        assert_eq!(offset, patch.len());
+
+        let operation = |index: Index| {
+            inspector
+                .records
+                .iter()
+                .filter(|x| x.index == index)
+                .map(|x| &x.operation)
+                .collect::<Vec<_>>()
+        };
+        assert!(matches!(operation(0)[..], [RecordOperation::Entry { .. }, RecordOperation::MapName { .. }]));
+        assert!(matches!(operation(1)[..], [RecordOperation::RemoveEntry]));
+        assert!(matches!(operation(3)[..], [RecordOperation::CopyEntry { source_index: 1, .. }]));
+    }
+
+    #[test]
+    fn display_like_unified_diff() {
+        let mut sender = sender();
+        let text = PatchInspector::new(&sender.patch(&new_data())).unwrap().to_string();
+        assert!(text.starts_with("patch version 1, diff algorithm 1"), "{}", text);
+        assert!(text.contains("=== index 0 entry"), "{}", text);
+        assert!(text.contains("@@ -6,0 +6,10 @@ insert\n+brave\n+new \n"), "{}", text);
+        assert!(text.contains("=== index 0 map name"), "{}", text);
+        assert!(text.contains("+greeting\n"), "{}", text);
+        assert!(text.contains("=== index 1 remove entry"), "{}", text);
+        assert!(text.contains("=== index 3 copy of index 1"), "{}", text);
+        // bytes which are not UTF-8 are shown as hex
+        assert!(text.contains("-[1 bytes]\n+[97]\n"), "{}", text);
+    }
+
+    #[test]
+    fn display_removed_bytes_of_reversible_patch() {
+        let mut sender = sender();
+        sender.set_reversible(true);
+        let patch = sender.patch(&[IndexedData::new(0, "Hello".as_bytes().to_vec())]);
+        let text = PatchInspector::new(&patch).unwrap().to_string();
+        assert!(text.contains("@@ -5,6 +5,0 @@ delete\n- World\n"), "{}", text);
+        assert!(text.contains("=== index 0 old entry, 1 values"), "{}", text);
+    }
+
+    #[test]
+    fn inspect_invalid_patch() {
+        let patch = sender().patch(&new_data());
+        let inspector = PatchInspector::new(&patch).unwrap();
+        // a patch which ends inside of the header or a record is truncated
+        let ends: Vec<usize> = inspector.records.iter().filter_map(|x| x.offset).collect();
+        for length in (0..patch.len()).filter(|x| !ends.contains(x)) {
+            assert!(PatchInspector::new(&patch[..length]).is_err(), "length {}", length);
+        }
+        assert!(PatchInspector::new(&[patch.clone(), vec![b'x', 0]].concat()).is_err());
+        // the control byte is checked before the index of the record is read
+        let offset = patch.len();
+        assert!(matches!(
+            PatchInspector::new(&[patch.clone(), vec![b'x', 0xff, 0xff]].concat()),
+            Err(SDDEError::UnknownControlByte { byte: b'x', offset: x }) if x == offset
+        ));
+    }
+
+    #[test]
+    fn inspect_version_0_patch() {
+        // the key name of index 1 is changed from "name" to "firstname" (web page sample)
+        let patch = [
+            10, 50, 54, 48, 48, 53, 49, 52, 53, 55, 55, 118, 1, 109, 21, 9, 114, 58, 0, 45, 4, 102, 105, 114, 115, 10, 105, 58, 4,
+            45, 5, 116, 110, 97, 109, 101,
+        ];
+        let inspector = PatchInspector::new(&patch).unwrap();
+        assert_eq!(inspector.header.version, 0);
+        assert_eq!(inspector.header_size, 11);
+        assert!(inspector.records.iter().all(|x| x.index == 1 && x.offset.is_none() && x.size.is_none()));
+        assert!(matches!(
+            inspector.records.iter().map(|x| &x.operation).collect::<Vec<_>>()[..],
+            [RecordOperation::MapName { .. }]
+        ));
+
+        let text = inspector.to_string();
+        assert!(text.starts_with("patch version 0"), "{}", text);
+        assert!(text.contains("crc 32363030353134353737 -> unverified"), "{}", text);
+        assert!(text.contains("=== index 1 map name\n@@ -0,4 +0,4 @@ replace\n-[4 bytes]\n+firs\n@@ -4,0 +4,5 @@ insert\n+tname\n"), "{}", text);
+
+        // the data of index 0 is changed from "test" to "test1"
+        let patch = [10, 50, 50, 53, 56, 54, 54, 50, 48, 56, 48, 118, 0, 6, 105, 58, 4, 45, 1, 49];
+        let text = PatchInspector::new(&patch).unwrap().to_string();
+        assert!(text.contains("=== index 0 entry\n@@ -4,0 +4,1 @@ insert\n+1\n"), "{}", text);
+
+        // a version 0 patch without differences has no records
+        assert!(PatchInspector::new(&[1, 0]).unwrap().records.is_empty());
+    }
+}
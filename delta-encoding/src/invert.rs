@@ -0,0 +1,93 @@
+use crate::data_difference::{DataDifference, Difference, DifferenceAction, Range};
+use crate::diff_algorithm::diff_algorithm_from_id;
+use crate::patch_format::{
+    diff_record, index_record, PatchHeader, PATCH_VERSION, RECORD_ENTRY, RECORD_MAP_NAME, RECORD_REMOVE_ENTRY,
+    RECORD_REMOVE_MAP_NAME,
+};
+use crate::{Index, RemovedValue, SDDEError, SimpleDirectDeltaEncoding};
+
+/// Patch which undoes a reversible patch (see [`SimpleDirectDeltaEncoding::set_reversible`])
+///
+///
+/// The inverted patch is applied to the state after the patch and restores the state before the patch
+/// (the CRC and the target CRC are swapped).<br/>
+/// Only patches of the built-in diff algorithms can be inverted, the inverted patch itself is not reversible.
+pub fn invert(patch: &[u8]) -> Result<Vec<u8>, SDDEError> {
+    let (header, _) = PatchHeader::from_bytes(patch)?;
+    if header.version == 0 {
+        return Err(SDDEError::NotReversible("Version 0 patches have no target CRC".to_owned()));
+    }
+    if diff_algorithm_from_id(header.diff_algorithm).is_none() {
+        return Err(SDDEError::UnknownDiffAlgorithm { id: header.diff_algorithm });
+    }
+
+    let mut records: Vec<u8> = Vec::new();
+    for (index, diff) in SimpleDirectDeltaEncoding::get_differences(patch)? {
+        if diff.data_changed || diff.remove_entry {
+            let old_data = removed_value(index, diff.old_data.as_ref())?;
+            if diff.data_changed && diff.copy_from.is_none() && !diff.remove_entry {
+                if old_data.existed {
+                    let diffs = DataDifference::invert(&diff.diffs, &old_data.values)?;
+                    records.extend(diff_record(RECORD_ENTRY, index, &diffs));
+                } else {
+                    records.extend(index_record(RECORD_REMOVE_ENTRY, index));
+                }
+            } else if old_data.existed {
+                // the old data is created again from empty data
+                records.extend(index_record(RECORD_REMOVE_ENTRY, index));
+                records.extend(diff_record(RECORD_ENTRY, index, &insert_value(index, old_data)?));
+            } else if diff.data_changed {
+                records.extend(index_record(RECORD_REMOVE_ENTRY, index));
+            }
+        }
+
+        if diff.map_name_changed.is_some() || diff.map_name_removed {
+            let old_map_name = removed_value(index, diff.old_map_name.as_ref())?;
+            match diff.map_name_changed.as_ref() {
+                Some(map_name_changed) if !diff.map_name_removed => {
+                    if old_map_name.existed {
+                        let diffs = DataDifference::invert(map_name_changed, &old_map_name.values)?;
+                        records.extend(diff_record(RECORD_MAP_NAME, index, &diffs));
+                    } else {
+                        records.extend(index_record(RECORD_REMOVE_MAP_NAME, index));
+                    }
+                },
+                _ if old_map_name.existed => {
+                    records.extend(index_record(RECORD_REMOVE_MAP_NAME, index));
+                    records.extend(diff_record(RECORD_MAP_NAME, index, &insert_value(index, old_map_name)?));
+                },
+                Some(_) => records.extend(index_record(RECORD_REMOVE_MAP_NAME, index)),
+                None => {},
+            }
+        }
+    }
+
+    let inverted_header = PatchHeader {
+        version: PATCH_VERSION,
+        diff_algorithm: header.diff_algorithm,
+        checksum_algorithm: header.checksum_algorithm,
+        crc: header.target_crc,
+        target_crc: header.crc,
+    };
+    Ok([inverted_header.to_bytes(), records].concat())
+}
+
+fn removed_value(index: Index, removed: Option<&RemovedValue>) -> Result<&RemovedValue, SDDEError> {
+    removed.ok_or_else(|| SDDEError::NotReversible(format!("The patch has no removed bytes for the index {}", index)))
+}
+
+/// Differences which insert the whole old value into empty data
+fn insert_value(index: Index, removed: &RemovedValue) -> Result<Vec<Difference>, SDDEError> {
+    let [value] = removed.values.as_slice() else {
+        return Err(SDDEError::NotReversible(format!("The patch has no old value for the index {}", index)));
+    };
+    if value.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(vec![Difference {
+        action: DifferenceAction::Insert,
+        range: Range::new(0, value.len()),
+        value: value.clone(),
+        is_open: false,
+    }])
+}
@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod invert {
+    use std::collections::BTreeMap;
+
+    use crate::tests::props;
+    use crate::*;
+
+    #[test]
+    fn undo_and_redo() {
+        let props = props();
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        sender.set_reversible(true);
+        sender.set_diff_algorithm(LcsDiff::default());
+        sender.change_index_mapping(0, "greeting".as_bytes());
+        let patch = sender.patch(&[
+            IndexedData::new(0, "Hello brave World".as_bytes().to_vec()),
+            IndexedData::new(2, "abcdefghijklmnopqrstuvwxyz".as_bytes().to_vec()),
+            IndexedData::new(3, "new".as_bytes().to_vec()),
+        ]);
+
+        let mut editor = SimpleDirectDeltaEncoding::new(&props);
+        let source_state = editor.get_state();
+        assert!(editor.apply_patch(&patch).is_ok());
+        let target_state = editor.get_state();
+        assert_eq!(target_state, sender.get_state());
+
+        let undo = invert(&patch).unwrap();
+        assert!(editor.apply_patch(&undo).is_ok());
+        assert_eq!(editor.get_state(), source_state);
+        assert!(editor.apply_patch(&patch).is_ok());
+        assert_eq!(editor.get_state(), target_state);
+    }
+
+    #[test]
+    fn undo_chained_patches() {
+        let props = props();
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        sender.set_reversible(true);
+        let mut current: BTreeMap<Index, Vec<u8>> = props.iter().map(|x| (x.index, x.data.clone())).collect();
+        let mut states = vec![sender.get_state()];
+        let mut patches = Vec::new();
+        for round in 0..30u32 {
+            match round % 3 {
+                0 => sender.set_diff_algorithm(PositionalDiff::default()),
+                1 => sender.set_diff_algorithm(LcsDiff::default()),
+                _ => sender.set_diff_algorithm(BlockDiff::new(4)),
+            }
+            let index = round * 7 % 5;
+            match round % 4 {
+                0 => {
+                    current.remove(&index);
+                },
+                1 => {
+                    // the old value of another index is stored as copy
+                    let value = current.get(&((index + 1) % 5)).cloned().unwrap_or_default();
+                    current.insert(index, value);
+                },
+                _ => {
+                    let value = current.entry(index).or_default();
+                    let position = (round as usize * 3) % (value.len() + 1);
+                    value.splice(position..position, format!("<{}>", round).into_bytes());
+                    let end = value.len().min(position + 8);
+                    value.drain(position.min(end)..end.saturating_sub(2));
+                },
+            }
+            if round % 5 == 0 {
+                sender.change_index_mapping(index, format!("key {}", round).as_bytes());
+            }
+            if round % 7 == 3 {
+                sender.remove_index_mapping((index + 2) % 5);
+            }
+            let new_data: Vec<IndexedData> = current.iter().map(|(index, data)| IndexedData::new(*index, data.clone())).collect();
+            patches.push(sender.patch(&new_data));
+            states.push(sender.get_state());
+        }
+
+        // undo every patch from the last to the first
+        let mut editor = sender.clone();
+        for (round, patch) in patches.iter().enumerate().rev() {
+            let result = editor.apply_patch(&invert(patch).unwrap());
+            assert!(result.is_ok(), "round {} {:?}", round, result);
+            assert_eq!(editor.get_state(), states[round], "round {}", round);
+        }
+    }
+
+    #[test]
+    fn invert_not_reversible_patch() {
+        let props = props();
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        let patch = sender.patch(&[IndexedData::new(0, "Hello".as_bytes().to_vec())]);
+        assert!(matches!(invert(&patch), Err(SDDEError::NotReversible(_))));
+    }
+
+    #[test]
+    fn reversible_patch_is_applied() {
+        let props = props();
+        let new_data = [IndexedData::new(0, "Hello".as_bytes().to_vec())];
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        let patch = sender.clone().patch(&new_data);
+        sender.set_reversible(true);
+        let reversible_patch = sender.patch(&new_data);
+        assert!(reversible_patch.len() > patch.len());
+
+        let mut receiver = SimpleDirectDeltaEncoding::new(&props);
+        assert!(receiver.apply_patch(&reversible_patch).is_ok());
+        assert_eq!(receiver.get_state(), sender.get_state());
+    }
+
+    #[test]
+    fn invert_differences() {
+        let data_old = "abcdefghijklmnopqrstuvwxyz".as_bytes();
+        let data_new = "mnopqrstuvwxyz-abcdefghijkl!".as_bytes();
+        for diffs in [
+            DataDifference::diff(data_old, data_new),
+            DataDifference::diff_myers(data_old, data_new),
+            DataDifference::diff_blocks(data_old, data_new, 4),
+        ] {
+            let removed = DataDifference::removed_bytes(data_old, &diffs).unwrap();
+            let inverted = DataDifference::invert(&diffs, &removed).unwrap();
+            assert_eq!(DataDifference::apply_diff(data_new, &inverted).unwrap(), data_old);
+        }
+    }
+}
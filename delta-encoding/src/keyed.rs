@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+
+use crate::data_difference::DataDifference;
+use crate::diff_algorithm::DiffAlgorithm;
+use crate::{Index, IndexedData, SDDEError, SimpleDirectDeltaEncoding};
+
+/// Minimum similarity of the values of a removed and an added key to detect a rename
+pub const DEFAULT_RENAME_THRESHOLD: f64 = 0.8;
+
+/// Key value data on top of the index mappings of [`SimpleDirectDeltaEncoding`]
+///
+///
+/// The indexes are allocated in the order of the keys, so the sender and the receiver have the same indexes for the same data.<br/>
+/// A key which is removed while a new key has the same or a similar value is sent as a rename
+/// (the index mapping changes and the value is patched on the index of the removed key).
+#[derive(Clone)]
+pub struct KeyedDeltaEncoding {
+    encoding: SimpleDirectDeltaEncoding,
+    keys: BTreeMap<Vec<u8>, Index>,
+    rename_threshold: f64,
+}
+
+impl KeyedDeltaEncoding {
+    /// Accepts any map with byte like keys and values (e.g. `&BTreeMap<Vec<u8>, Vec<u8>>` or `&HashMap<String, Vec<u8>>`)
+    pub fn new<K: AsRef<[u8]>, V: AsRef<[u8]>>(data: impl IntoIterator<Item = (K, V)>) -> KeyedDeltaEncoding {
+        let data = Self::to_map(data);
+        let mut keys: BTreeMap<Vec<u8>, Index> = BTreeMap::new();
+        let mut indexed_data: Vec<IndexedData> = Vec::with_capacity(data.len());
+        for (index, (key, value)) in data.into_iter().enumerate() {
+            let index = index as Index;
+            keys.insert(key, index);
+            indexed_data.push(IndexedData::new(index, value));
+        }
+        let mut encoding = SimpleDirectDeltaEncoding::new(&indexed_data);
+        for (key, index) in keys.iter() {
+            encoding.change_index_mapping(*index, key);
+        }
+        // the initial keys are known by the receiver and are not part of the next patch
+        encoding.apply_index_mappings();
+        KeyedDeltaEncoding {
+            encoding,
+            keys,
+            rename_threshold: DEFAULT_RENAME_THRESHOLD,
+        }
+    }
+
+    /// Current key value data
+    pub fn get_map(&self) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        self.keys
+            .iter()
+            .filter_map(|(key, index)| {
+                self.encoding
+                    .data_collection
+                    .get(index)
+                    .map(|data| (key.clone(), data.data.clone()))
+            })
+            .collect()
+    }
+
+    /// Index of the key in the underlying [`SimpleDirectDeltaEncoding`]
+    pub fn get_index(&self, key: &[u8]) -> Option<Index> {
+        self.keys.get(key).copied()
+    }
+
+    pub fn encoding(&self) -> &SimpleDirectDeltaEncoding {
+        &self.encoding
+    }
+
+    /// Change the algorithm used to create the differences in the next patches
+    pub fn set_diff_algorithm(&mut self, diff_algorithm: impl DiffAlgorithm + 'static) {
+        self.encoding.set_diff_algorithm(diff_algorithm);
+    }
+
+    /// Change the minimum similarity (0.0 to 1.0) of the values of a removed and an added key to detect a rename
+    ///
+    ///
+    /// The similarity is the count of the common bytes divided by the length of the longer value.<br/>
+    /// With 1.0 only identical values are renames, with a value above 1.0 no renames are detected.
+    pub fn set_rename_threshold(&mut self, threshold: f64) {
+        self.rename_threshold = threshold;
+    }
+
+    /// Patch the data with the new key value data and return the diff data
+    ///
+    ///
+    /// * Changed values of existing keys are differences of the index of the key
+    /// * A new key with the same or a similar value as a removed key is a rename of the index mapping
+    /// * Other new keys get a new index (after the highest index used so far)
+    /// * Removed keys remove the entry and the index mapping
+    pub fn patch<K: AsRef<[u8]>, V: AsRef<[u8]>>(&mut self, new_data: impl IntoIterator<Item = (K, V)>) -> Vec<u8> {
+        let new_data = Self::to_map(new_data);
+        let old_data = self.get_map();
+        let mut removed_keys: Vec<&Vec<u8>> = old_data.keys().filter(|key| !new_data.contains_key(*key)).collect();
+
+        let mut keys: BTreeMap<Vec<u8>, Index> = BTreeMap::new();
+        let mut added_keys: Vec<&Vec<u8>> = Vec::new();
+        for key in new_data.keys() {
+            if let Some(index) = self.keys.get(key) {
+                keys.insert(key.clone(), *index);
+            } else {
+                added_keys.push(key);
+            }
+        }
+
+        // identical values are matched first, so a similar value does not take the removed key of an identical value
+        let passes: &[bool] = if self.rename_threshold > 1.0 { &[] } else { &[true, false] };
+        for identical in passes {
+            added_keys.retain(|key| {
+                let value = &new_data[*key];
+                let renamed = if *identical {
+                    removed_keys.iter().position(|removed| old_data[*removed] == *value)
+                } else {
+                    self.find_similar(&old_data, &removed_keys, value)
+                };
+                let Some(position) = renamed else {
+                    return true;
+                };
+                let index = self.keys[removed_keys.remove(position)];
+                self.encoding.change_index_mapping(index, key);
+                keys.insert((*key).clone(), index);
+                false
+            });
+        }
+
+        for (index, key) in (self.next_index()..).zip(added_keys) {
+            self.encoding.change_index_mapping(index, key);
+            keys.insert(key.clone(), index);
+        }
+        for key in removed_keys {
+            self.encoding.remove_index_mapping(self.keys[key]);
+        }
+
+        let indexed_data: Vec<IndexedData> = keys
+            .iter()
+            .map(|(key, index)| IndexedData::new(*index, new_data[key].clone()))
+            .collect();
+        self.keys = keys;
+        self.encoding.patch(&indexed_data)
+    }
+
+    /// Apply the patch to the data and return the patched key value data
+    ///
+    ///
+    /// On an error the data stays untouched.
+    pub fn apply_patch(&mut self, diff_data: &[u8]) -> Result<BTreeMap<Vec<u8>, Vec<u8>>, SDDEError> {
+        self.encoding.apply_patch(diff_data)?;
+        let index_mapping = self.encoding.get_index_mapping();
+        self.keys = self
+            .encoding
+            .data_collection
+            .keys()
+            .filter_map(|index| index_mapping.get(index).map(|key| (key.current.clone(), *index)))
+            .collect();
+        Ok(self.get_map())
+    }
+
+    /// Position of the removed key with the most similar value above the rename threshold
+    fn find_similar(&self, old_data: &BTreeMap<Vec<u8>, Vec<u8>>, removed_keys: &[&Vec<u8>], value: &[u8]) -> Option<usize> {
+        let mut best: Option<(usize, f64)> = None;
+        for (position, removed) in removed_keys.iter().enumerate() {
+            let old_value = &old_data[*removed];
+            // the similarity can not be higher than the length ratio
+            let (shorter, longer) = (old_value.len().min(value.len()), old_value.len().max(value.len()));
+            if longer > 0 && (shorter as f64 / longer as f64) < self.rename_threshold {
+                continue;
+            }
+            let similarity = DataDifference::similarity(old_value, value);
+            if similarity >= self.rename_threshold && best.is_none_or(|(_, b)| similarity > b) {
+                best = Some((position, similarity));
+            }
+        }
+        best.map(|(position, _)| position)
+    }
+
+    /// Index after the highest index of the entries and index mappings
+    fn next_index(&self) -> Index {
+        let index_mapping = self.encoding.get_index_mapping();
+        let last_mapped = index_mapping.keys().next_back();
+        let last_data = self.encoding.data_collection.keys().next_back();
+        last_mapped.max(last_data).map(|index| index + 1).unwrap_or_default()
+    }
+
+    fn to_map<K: AsRef<[u8]>, V: AsRef<[u8]>>(data: impl IntoIterator<Item = (K, V)>) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        data.into_iter()
+            .map(|(key, value)| (key.as_ref().to_vec(), value.as_ref().to_vec()))
+            .collect()
+    }
+}
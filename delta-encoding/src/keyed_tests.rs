@@ -0,0 +1,158 @@
+#[cfg(test)]
+mod keyed {
+    use std::collections::{BTreeMap, HashMap};
+
+    use crate::*;
+
+    fn map(entries: &[(&str, &str)]) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.as_bytes().to_vec(), value.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn patch_and_apply_map() {
+        let base = map(&[("name", "John"), ("age", "30"), ("city", "")]);
+        let changes = map(&[("name", "John"), ("age", "31"), ("city", "New York"), ("zip", "10001")]);
+        let mut sender = KeyedDeltaEncoding::new(&base);
+        let diff_data = sender.patch(&changes);
+        assert_eq!(sender.get_map(), changes);
+
+        let mut receiver = KeyedDeltaEncoding::new(&base);
+        assert_eq!(receiver.apply_patch(&diff_data).unwrap(), changes);
+        assert_eq!(receiver.get_index("zip".as_bytes()), sender.get_index("zip".as_bytes()));
+    }
+
+    #[test]
+    fn patch_string_keys() {
+        let base: HashMap<String, Vec<u8>> = HashMap::from([
+            ("b".to_string(), "2".as_bytes().to_vec()),
+            ("a".to_string(), "1".as_bytes().to_vec()),
+        ]);
+        let mut changes = base.clone();
+        changes.remove("a");
+        changes.insert("c".to_string(), "3".as_bytes().to_vec());
+        let mut sender = KeyedDeltaEncoding::new(&base);
+        let diff_data = sender.patch(&changes);
+
+        let mut receiver = KeyedDeltaEncoding::new(&base);
+        assert_eq!(receiver.apply_patch(&diff_data).unwrap(), map(&[("b", "2"), ("c", "3")]));
+    }
+
+    #[test]
+    fn patch_rename_key() {
+        let base = map(&[("name", "John"), ("age", "30")]);
+        let changes = map(&[("firstname", "John"), ("age", "30")]);
+        let mut sender = KeyedDeltaEncoding::new(&base);
+        let index = sender.get_index("name".as_bytes());
+        let diff_data = sender.patch(&changes);
+        assert_eq!(sender.get_index("firstname".as_bytes()), index);
+
+        // only the index mapping changes
+        let differences = SimpleDirectDeltaEncoding::get_differences(&diff_data).unwrap();
+        assert_eq!(differences.len(), 1);
+        let difference = differences.get(&index.unwrap()).unwrap();
+        assert!(!difference.remove_entry && difference.diffs.is_empty());
+        assert!(difference.map_name_changed.is_some());
+
+        let mut receiver = KeyedDeltaEncoding::new(&base);
+        assert_eq!(receiver.apply_patch(&diff_data).unwrap(), changes);
+    }
+
+    #[test]
+    fn patch_rename_key_with_similar_value() {
+        let base = map(&[("address", "221B Baker Street, London"), ("age", "30")]);
+        let changes = map(&[("street", "221B Baker Street, London NW1"), ("age", "30")]);
+        let mut sender = KeyedDeltaEncoding::new(&base);
+        let index = sender.get_index("address".as_bytes());
+        let diff_data = sender.patch(&changes);
+        assert_eq!(sender.get_index("street".as_bytes()), index);
+
+        let differences = SimpleDirectDeltaEncoding::get_differences(&diff_data).unwrap();
+        assert_eq!(differences.len(), 1);
+        let difference = differences.get(&index.unwrap()).unwrap();
+        assert!(!difference.diffs.is_empty() && difference.map_name_changed.is_some());
+
+        let mut receiver = KeyedDeltaEncoding::new(&base);
+        assert_eq!(receiver.apply_patch(&diff_data).unwrap(), changes);
+    }
+
+    #[test]
+    fn rename_threshold() {
+        let base = map(&[("a", "0123456789")]);
+        let changes = map(&[("b", "0123456XYZ")]);
+        let mut sender = KeyedDeltaEncoding::new(&base);
+        sender.patch(&changes);
+        assert_eq!(sender.get_index("b".as_bytes()), Some(1));
+
+        let mut sender = KeyedDeltaEncoding::new(&base);
+        sender.set_rename_threshold(0.7);
+        sender.patch(&changes);
+        assert_eq!(sender.get_index("b".as_bytes()), Some(0));
+
+        // identical values are no renames if the detection is disabled
+        let mut sender = KeyedDeltaEncoding::new(&base);
+        sender.set_rename_threshold(f64::INFINITY);
+        sender.patch(&map(&[("b", "0123456789")]));
+        assert_eq!(sender.get_index("b".as_bytes()), Some(1));
+    }
+
+    #[test]
+    fn identical_rename_is_preferred() {
+        let base = map(&[("a", "value 1"), ("b", "value 2")]);
+        // "c" is similar to "a" and "b" but sorted first, "d" is identical to "a"
+        let changes = map(&[("c", "value 3"), ("d", "value 1")]);
+        let mut sender = KeyedDeltaEncoding::new(&base);
+        sender.set_rename_threshold(0.5);
+        let diff_data = sender.patch(&changes);
+        assert_eq!(sender.get_index("d".as_bytes()), Some(0));
+        assert_eq!(sender.get_index("c".as_bytes()), Some(1));
+
+        let mut receiver = KeyedDeltaEncoding::new(&base);
+        assert_eq!(receiver.apply_patch(&diff_data).unwrap(), changes);
+    }
+
+    #[test]
+    fn removed_indexes_are_not_reused() {
+        let base = map(&[("a", "1"), ("b", "2")]);
+        let changes = map(&[("a", "1"), ("c", "3")]);
+        let mut sender = KeyedDeltaEncoding::new(&base);
+        let diff_data = sender.patch(&changes);
+        assert_eq!(sender.get_index("c".as_bytes()), Some(2));
+
+        let mut receiver = KeyedDeltaEncoding::new(&base);
+        assert_eq!(receiver.apply_patch(&diff_data).unwrap(), changes);
+        assert_eq!(receiver.get_index("c".as_bytes()), Some(2));
+        assert_eq!(receiver.get_index("b".as_bytes()), None);
+        assert!(!receiver.encoding().get_index_mapping().contains_key(&1));
+    }
+
+    #[test]
+    fn apply_invalid_patch() {
+        let base = map(&[("a", "1")]);
+        let mut receiver = KeyedDeltaEncoding::new(&base);
+        assert!(receiver.apply_patch(&[1, 2, 3]).is_err());
+        assert_eq!(receiver.get_map(), base);
+    }
+
+    #[test]
+    fn chain_sequential_patches() {
+        let steps = [
+            map(&[("name", "John"), ("age", "30"), ("city", "")]),
+            map(&[("firstname", "John"), ("age", "30"), ("city", "New York")]),
+            map(&[("firstname", "John"), ("age", "31")]),
+            map(&[("firstname", "John"), ("lastname", "Doe"), ("age", "31"), ("zip", "10001")]),
+            map(&[("first", "John"), ("lastname", "Doe"), ("postcode", "10001")]),
+            map(&[]),
+            map(&[("name", "Jane")]),
+        ];
+        let mut sender = KeyedDeltaEncoding::new(&steps[0]);
+        let mut receiver = KeyedDeltaEncoding::new(&steps[0]);
+        for step in steps.iter().skip(1) {
+            let diff_data = sender.patch(step);
+            assert_eq!(&receiver.apply_patch(&diff_data).unwrap(), step);
+            assert_eq!(receiver.encoding().crc, sender.encoding().crc);
+        }
+    }
+}
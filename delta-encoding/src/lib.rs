@@ -1,29 +1,170 @@
+mod checksum;
+#[cfg(test)]
+mod checksum_tests;
+#[cfg(feature = "cli")]
+mod cli;
+#[cfg(all(test, feature = "cli"))]
+mod cli_tests;
+mod compose;
+#[cfg(test)]
+mod compose_tests;
 mod data_difference;
 #[cfg(test)]
 mod data_difference_tests;
+mod diff_algorithm;
+#[cfg(test)]
+mod diff_algorithm_tests;
+mod history;
+#[cfg(test)]
+mod history_tests;
+mod inspect;
+#[cfg(test)]
+mod inspect_tests;
+mod invert;
+#[cfg(test)]
+mod invert_tests;
+mod keyed;
+#[cfg(test)]
+mod keyed_tests;
+mod merge;
+#[cfg(test)]
+mod merge_tests;
+#[cfg(feature = "serde")]
+mod patch_document;
+#[cfg(all(test, feature = "serde"))]
+mod patch_document_tests;
+mod patch_format;
+#[cfg(test)]
+mod patch_format_tests;
+mod rebase;
+#[cfg(test)]
+mod rebase_tests;
+mod snapshot;
+#[cfg(test)]
+mod snapshot_tests;
+mod stream;
+#[cfg(test)]
+mod stream_tests;
 #[cfg(test)]
 mod tests;
-
-use std::collections::BTreeMap;
-
-use data_difference::*;
-use dispnet_hash::{DispnetHash, HashType};
+mod tree;
+#[cfg(test)]
+mod tree_tests;
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Write;
+use std::sync::Arc;
+
+pub use checksum::*;
+#[cfg(feature = "cli")]
+pub use cli::*;
+pub use compose::*;
+pub use data_difference::*;
+pub use diff_algorithm::*;
+pub use history::*;
+pub use inspect::*;
+pub use invert::*;
+pub use keyed::*;
+pub use merge::*;
+#[cfg(feature = "serde")]
+pub use patch_document::*;
+pub use patch_format::*;
+pub use rebase::*;
+pub use snapshot::*;
+pub use stream::*;
+pub use tree::*;
+
+/// Index of the data entries and index mappings, stored as varint in the patch
+pub type Index = u32;
 
 #[derive(Debug)]
 pub enum SDDEError {
     CRC(String),
     DifferenceInvalid(String),
+    /// The patch ended before the value at the offset could be read
+    Truncated { offset: usize },
+    /// The byte at the offset is not a known record, action or separator
+    UnknownControlByte { byte: u8, offset: usize },
+    /// A length, range or index does not fit into its type
+    LengthOverflow,
+    /// The range of a difference is outside of the data (index is `None` for data without an index)
+    RangeOutOfBounds { index: Option<Index>, range: Range },
+    /// The patch was created with a newer format version
+    UnsupportedVersion { version: u8, offset: usize },
+    /// The patch was created with a diff algorithm which is not known
+    UnknownDiffAlgorithm { id: u8 },
+    /// The CRC values of the patch were created with a checksum algorithm which is not known
+    UnknownChecksumAlgorithm { id: u8 },
+    /// The entry of a copy record does not exist in the data
+    UnknownSourceIndex { index: Index },
+    /// The state after applying the patch does not have the target CRC of the patch (the changes are rolled back)
+    TargetMismatch { expected: Vec<u8>, actual: Vec<u8> },
+    /// The revision is not part of the patch history
+    UnknownRevision { revision: Revision },
+    /// The patches can not be combined into a single patch (e.g. they are not consecutive)
+    NotComposable(String),
+    /// The patch can not be inverted (e.g. it was not created as reversible patch)
+    NotReversible(String),
+    /// The patch can not be rebased onto the other patch (e.g. they are not created for the same state)
+    NotRebasable(String),
+    /// A file or directory could not be read or written
+    Io(String),
+    /// The path of a file is not a relative path inside of the directory
+    InvalidPath(String),
 }
 
+impl std::fmt::Display for SDDEError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SDDEError::CRC(message) => write!(f, "CRC error: {}", message),
+            SDDEError::DifferenceInvalid(message) => write!(f, "Invalid difference: {}", message),
+            SDDEError::Truncated { offset } => write!(f, "Patch is truncated at offset {}", offset),
+            SDDEError::UnknownControlByte { byte, offset } => {
+                write!(f, "Unknown control byte {} at offset {}", byte, offset)
+            }
+            SDDEError::LengthOverflow => write!(f, "Length overflow"),
+            SDDEError::RangeOutOfBounds { index, range } => write!(
+                f,
+                "Range {}-{} is out of bounds for index {:?}",
+                range.start, range.length, index
+            ),
+            SDDEError::UnsupportedVersion { version, offset } => {
+                write!(f, "Unsupported patch version {} at offset {}", version, offset)
+            }
+            SDDEError::UnknownDiffAlgorithm { id } => write!(f, "Unknown diff algorithm {}", id),
+            SDDEError::UnknownChecksumAlgorithm { id } => write!(f, "Unknown checksum algorithm {}", id),
+            SDDEError::UnknownSourceIndex { index } => write!(f, "Unknown source index {} of a copy", index),
+            SDDEError::TargetMismatch { expected, actual } => {
+                write!(f, "Target CRC {:?} does not match the patched state {:?}", expected, actual)
+            }
+            SDDEError::UnknownRevision { revision } => write!(f, "Unknown revision {}", revision),
+            SDDEError::NotComposable(message) => write!(f, "Patches can not be composed: {}", message),
+            SDDEError::NotReversible(message) => write!(f, "Patch can not be inverted: {}", message),
+            SDDEError::NotRebasable(message) => write!(f, "Patch can not be rebased: {}", message),
+            SDDEError::Io(message) => write!(f, "IO error: {}", message),
+            SDDEError::InvalidPath(message) => write!(f, "Invalid path: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SDDEError {}
+
 #[derive(Clone)]
 pub struct SimpleDirectDeltaEncoding {
-    pub data_collection: BTreeMap<u8, IndexedData>,
+    pub data_collection: BTreeMap<Index, IndexedData>,
     pub crc: Vec<u8>,
-    index_mapping: BTreeMap<u8, Vec<u8>>,
-    last_index_mapping: BTreeMap<u8, HistoryValue>,
+    /// Changed index mappings for the next patch (`None` for removed mappings)
+    index_mapping: BTreeMap<Index, Option<Vec<u8>>>,
+    last_index_mapping: BTreeMap<Index, HistoryValue>,
+    diff_algorithm: Arc<dyn DiffAlgorithm>,
+    checksum_algorithm: ChecksumAlgorithm,
+    /// The patches contain the removed bytes, so they can be inverted
+    reversible: bool,
 }
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HistoryValue {
     pub current: Vec<u8>,
     pub last: Vec<u8>,
@@ -44,47 +185,102 @@ impl HistoryValue {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IndexedData {
-    pub index: u8,
+    pub index: Index,
     pub data: Vec<u8>,
 }
 
 impl IndexedData {
-    pub fn new(index: u8, data: Vec<u8>) -> IndexedData {
+    pub fn new(index: Index, data: Vec<u8>) -> IndexedData {
         IndexedData { index, data }
     }
 }
 
 #[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EntryDifference {
     pub remove_entry: bool,
+    /// The diffs are the data of the index (false if only the index mapping of the index changes)
+    pub data_changed: bool,
     pub diffs: Vec<Difference>,
     pub map_name_changed: Option<Vec<Difference>>,
+    pub map_name_removed: bool,
+    /// The diffs are applied to the old data of this index instead of the own data
+    pub copy_from: Option<Index>,
+    /// Removed bytes of the data (only in reversible patches)
+    pub old_data: Option<RemovedValue>,
+    /// Removed bytes of the index mapping (only in reversible patches)
+    pub old_map_name: Option<RemovedValue>,
+}
+
+/// Bytes of an index which are removed or replaced by a reversible patch
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RemovedValue {
+    /// The data or index mapping existed before the patch
+    pub existed: bool,
+    /// Removed bytes of every difference, or the whole old value if the value is replaced as a whole (copy or remove)
+    pub values: Vec<Vec<u8>>,
+}
+
+impl RemovedValue {
+    pub fn new(existed: bool, values: Vec<Vec<u8>>) -> RemovedValue {
+        RemovedValue { existed, values }
+    }
 }
 
 impl EntryDifference {
     pub fn new(diffs: Vec<Difference>) -> EntryDifference {
         EntryDifference {
             remove_entry: false,
+            data_changed: true,
             diffs,
             map_name_changed: None,
+            map_name_removed: false,
+            copy_from: None,
+            old_data: None,
+            old_map_name: None,
         }
     }
 
     pub fn remove_entry() -> EntryDifference {
         EntryDifference {
             remove_entry: true,
+            data_changed: false,
             diffs: Vec::new(),
             map_name_changed: None,
+            map_name_removed: false,
+            copy_from: None,
+            old_data: None,
+            old_map_name: None,
+        }
+    }
+
+    pub fn copy_entry(source_index: Index, diffs: Vec<Difference>) -> EntryDifference {
+        EntryDifference {
+            remove_entry: false,
+            data_changed: true,
+            diffs,
+            map_name_changed: None,
+            map_name_removed: false,
+            copy_from: Some(source_index),
+            old_data: None,
+            old_map_name: None,
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IndexedDataResult {
-    pub index: u8,
+    pub index: Index,
     pub data: Vec<u8>,
     pub map_name_changed: Option<Vec<u8>>,
+    /// The index mapping of the index was removed
+    pub map_name_removed: bool,
+    /// The data entry of the index was removed (the data is empty)
+    pub entry_removed: bool,
 }
 
 impl IndexedDataResult {
@@ -93,30 +289,22 @@ impl IndexedDataResult {
             index: index_data.index,
             data: index_data.data.clone(),
             map_name_changed: None,
+            map_name_removed: false,
+            entry_removed: false,
         }
     }
 }
 
 impl SimpleDirectDeltaEncoding {
     pub fn new(data: &[IndexedData]) -> SimpleDirectDeltaEncoding {
-        let data = Self::get_sorted(data);
-        let bytes = Self::fold_indexed_data(&data);
-        let crc = DispnetHash::create(HashType::CRC, &bytes, None);
-        let mut data_map: BTreeMap<u8, IndexedData> = BTreeMap::new();
-        for indexed_data in data {
-            data_map.insert(indexed_data.index, indexed_data.clone());
-        }
-        SimpleDirectDeltaEncoding {
-            data_collection: data_map,
-            crc: crc.digest_value.clone(),
-            index_mapping: BTreeMap::new(),
-            last_index_mapping: BTreeMap::new(),
-        }
+        let mut encoding = Self::load(data, Vec::new());
+        encoding.update_crc();
+        encoding
     }
 
     pub fn load(data: &[IndexedData], crc: Vec<u8>) -> SimpleDirectDeltaEncoding {
         let data = Self::get_sorted(data);
-        let mut data_map: BTreeMap<u8, IndexedData> = BTreeMap::new();
+        let mut data_map: BTreeMap<Index, IndexedData> = BTreeMap::new();
         for indexed_data in data {
             data_map.insert(indexed_data.index, indexed_data.clone());
         }
@@ -125,246 +313,506 @@ impl SimpleDirectDeltaEncoding {
             crc,
             index_mapping: BTreeMap::new(),
             last_index_mapping: BTreeMap::new(),
+            diff_algorithm: Arc::new(PositionalDiff::default()),
+            checksum_algorithm: ChecksumAlgorithm::default(),
+            reversible: false,
         }
     }
 
-    /// Current state of the data
+    /// Current state of the data, the CRC is created from these bytes
     /// 
     /// 
-    /// * Indexed data and index mappings are stored in bytes
-    /// * The data is always in the same order (indexed data first, index mappings last ...)
+    /// The state has the following format:<br/>
+    /// [Entry count, Entry 1, Entry 2, ..., Mapping count, Mapping 1, Mapping 2, ...]
+    /// * An Entry is [Index, Data length, Data] ordered by the index
+    /// * A Mapping is [Index, Name length, Name] ordered by the index (only the applied index mappings)
+    /// * The counts, indexes and lengths are varints, so different states never have the same bytes
     pub fn get_state(&self) -> Vec<u8> {
-        let mut bytes = Self::fold_indexed_data(&self.data_collection.values().cloned().collect::<Vec<IndexedData>>());
-        for (_, value) in self.last_index_mapping.iter() {
-            bytes.extend(value.current.clone());
-        }
+        let mut bytes: Vec<u8> = Vec::new();
+        self.write_state(|part| bytes.extend(part));
         bytes
     }
 
+    fn write_state(&self, write: impl FnMut(&[u8])) {
+        Self::write_state_parts(
+            self.data_collection.iter().map(|(index, data)| (index, data.data.as_slice())),
+            self.last_index_mapping.iter().map(|(index, value)| (index, value.current.as_slice())),
+            write,
+        );
+    }
+
+    /// Passes the state bytes of the data and index mappings (both ordered by the index) in parts to `write`
+    fn write_state_parts<'a>(
+        data: impl ExactSizeIterator<Item = (&'a Index, &'a [u8])>,
+        index_mapping: impl ExactSizeIterator<Item = (&'a Index, &'a [u8])>,
+        mut write: impl FnMut(&[u8]),
+    ) {
+        write(&Varint::to_bytes(data.len()));
+        for (index, value) in data {
+            Self::write_state_value(*index, value, &mut write);
+        }
+        write(&Varint::to_bytes(index_mapping.len()));
+        for (index, value) in index_mapping {
+            Self::write_state_value(*index, value, &mut write);
+        }
+    }
+
+    fn write_state_value(index: Index, value: &[u8], write: &mut impl FnMut(&[u8])) {
+        write(&Varint::to_bytes(index as usize));
+        write(&Varint::to_bytes(value.len()));
+        write(value);
+    }
+
+    /// Checksum of the current state, the state bytes are passed to the hasher without a copy
+    pub(crate) fn state_checksum(&self, checksum_algorithm: ChecksumAlgorithm) -> Vec<u8> {
+        let mut hasher = checksum_algorithm.hasher();
+        self.write_state(|part| hasher.update(part));
+        hasher.finish()
+    }
+
+    /// Creates the CRC of the current state
+    fn update_crc(&mut self) {
+        self.crc = self.state_checksum(self.checksum_algorithm);
+    }
+
+    /// CRC of the state after a patch to the new data, with the pending index mapping changes applied
+    fn target_crc(&self, new_data: &[IndexedData]) -> Vec<u8> {
+        let data: BTreeMap<Index, &[u8]> = new_data.iter().map(|x| (x.index, x.data.as_slice())).collect();
+        let mut index_mapping: BTreeMap<Index, &[u8]> = self
+            .last_index_mapping
+            .iter()
+            .map(|(index, value)| (*index, value.current.as_slice()))
+            .collect();
+        for (index, map_name) in self.index_mapping.iter() {
+            match map_name {
+                Some(map_name) => index_mapping.insert(*index, map_name),
+                None => index_mapping.remove(index),
+            };
+        }
+        let mut hasher = self.checksum_algorithm.hasher();
+        Self::write_state_parts(
+            data.iter().map(|(index, value)| (index, *value)),
+            index_mapping.iter().map(|(index, value)| (index, *value)),
+            |part| hasher.update(part),
+        );
+        hasher.finish()
+    }
+
+    /// Change the algorithm used to create the differences in the next patches
+    ///
+    ///
+    /// The id of the algorithm is stored in the patch, the receiver applies built-in algorithms independent of its own algorithm.<br/>
+    /// Patches of a custom algorithm can only be applied by a receiver with the same algorithm.
+    pub fn set_diff_algorithm(&mut self, diff_algorithm: impl DiffAlgorithm + 'static) {
+        self.diff_algorithm = Arc::new(diff_algorithm);
+    }
+
+    /// Change the algorithm of the CRC values, the CRC of the current data is created with the new algorithm
+    ///
+    ///
+    /// The id of the algorithm is stored in the patch, the receiver validates the patch independent of its own algorithm.
+    pub fn set_checksum_algorithm(&mut self, checksum_algorithm: ChecksumAlgorithm) {
+        self.checksum_algorithm = checksum_algorithm;
+        self.update_crc();
+    }
+
+    /// Store the removed and replaced bytes (data and index mapping names) in the next patches, so they can be undone with [`invert`]
+    ///
+    ///
+    /// Receivers ignore the removed bytes, reversible patches are only larger.
+    pub fn set_reversible(&mut self, reversible: bool) {
+        self.reversible = reversible;
+    }
+
     /// Change the index mapping for the given index
-    pub fn change_index_mapping(&mut self, index: u8, key: &[u8]) {
-        self.index_mapping.insert(index, key.to_owned());
+    pub fn change_index_mapping(&mut self, index: Index, key: &[u8]) {
+        self.index_mapping.insert(index, Some(key.to_owned()));
+    }
+
+    /// Remove the index mapping for the given index, the data of the index is kept
+    pub fn remove_index_mapping(&mut self, index: Index) {
+        self.index_mapping.insert(index, None);
     }
 
     /// Stores all index mappings in the last index mapping collection.
     /// 
     /// 
     /// This is useful when the changed index mappings should not be included in the next patch.<br/>
-    /// After creating or applying a patch this same logic will be applied.
+    /// After creating or applying a patch this same logic will be applied.<br/>
+    /// The index mappings are part of the state, so the CRC is created again.
     pub fn apply_index_mappings(&mut self) {
-        for (index, key) in self.index_mapping.iter() {
-            if let Some(value) = self.last_index_mapping.get_mut(index) {
-                value.set(key.to_owned());
-            } else {
-                self.last_index_mapping.insert(*index, HistoryValue::new(key.to_owned()));
-            }
+        for (index, key) in std::mem::take(&mut self.index_mapping) {
+            self.set_last_index_mapping(index, key);
         }
-        self.index_mapping.clear();
+        self.update_crc();
     }
 
     /// Patch the data with the new data and return the diff data
     ///
     ///
     /// The diff data is a byte array with the following format:<br/>
-    /// [CRC length, CRC value, Difference 1, Difference 2, ...]<br/>
-    /// * The CRC length is a single byte that represents the length of the CRC value
-    /// * The CRC value is the hash of the data before patching
-    /// * The Difference is a struct that represents the difference between the old and new data
+    /// [Header, Record 1, Record 2, ...]<br/>
+    /// * The Header contains the magic bytes, the format version, the diff and checksum algorithm ids and the CRC values (see [`PatchHeader::to_bytes`])
+    /// * The CRC value is the hash of the state before patching (see [`Self::get_state`])
+    /// * The Target CRC value is the hash of the state after patching (data and index mappings, see [`Self::get_state`])
+    /// * A Record starts with a control byte and the index as varint followed by the payload of the record
+    ///
+    ///
+    /// The Records have the following formats:
+    /// * Entry [`RECORD_ENTRY`, Index, Difference count, Difference 1, Difference 2, ...]
+    /// * Copy entry [`RECORD_COPY_ENTRY`, Index, Source index, Difference count, Difference 1, Difference 2, ...]
+    /// * Remove entry [`RECORD_REMOVE_ENTRY`, Index]
+    /// * Map name [`RECORD_MAP_NAME`, Index, Difference count, Difference 1, Difference 2, ...]
+    /// * Remove map name [`RECORD_REMOVE_MAP_NAME`, Index]
+    /// * Removed bytes [`RECORD_OLD_ENTRY` or `RECORD_OLD_MAP_NAME`, Index, Existed, Value count, Value length 1, Value 1, ...] (only in reversible patches)
+    ///
+    /// A Remove record with an Entry or Map name record of the same index (e.g. in a composed patch) creates the data or name again from empty data.
     ///
     ///
     /// The Difference is a byte array with the following format:
     /// [Action, Range start, Range length, Value]
     /// The Action is a single byte that represents the action that should be taken
-    /// The Range start is a variable length byte array that represents the start index of the range
-    /// The Range length is a variable length byte array that represents the length of the range
-    /// The Value is a variable length byte array that represents the value that should be inserted
+    /// The Range start is a varint that represents the start index of the range
+    /// The Range length is a varint that represents the length of the range
+    /// The Value has the Range length and represents the value that should be inserted
     /// The Value is only present in the Replace and Insert actions
+    ///
+    ///
+    /// Data which was the old value of another index (e.g. moved to a different index) is stored as a copy of that index.
     pub fn patch(&mut self, new_data: &[IndexedData]) -> Vec<u8> {
+        let header = self.patch_header(new_data);
+        let mut diff_data: Vec<u8> = header.to_bytes();
+        let Ok(()) = self.write_records(new_data, header.target_crc, |record| {
+            diff_data.extend(record);
+            Ok::<(), std::convert::Infallible>(())
+        });
+        diff_data
+    }
+
+    /// Patch the data with the new data and write the patch to the writer, every record is written when its index is diffed
+    ///
+    ///
+    /// The bytes are the same as the bytes of [`SimpleDirectDeltaEncoding::patch`], the target CRC is created from the new data before the records are written.<br/>
+    /// The data is only changed when the whole patch is written, on a write error the data stays untouched.<br/>
+    /// The state for the target CRC is passed in parts to the hasher and the new data is only sorted by reference,
+    /// except for [`ChecksumAlgorithm::DispnetCrc`] which collects the state bytes before hashing (no incremental API).
+    pub fn patch_to_writer<W: Write>(&mut self, new_data: &[IndexedData], writer: W) -> Result<W, SDDEError> {
+        let header = self.patch_header(new_data);
+        let mut patch_writer = PatchWriter::new(writer, &header)?;
+        self.write_records(new_data, header.target_crc, |record| patch_writer.write_record(&record))?;
+        patch_writer.finish()
+    }
+
+    /// Header of the next patch to the new data
+    fn patch_header(&self, new_data: &[IndexedData]) -> PatchHeader {
+        let mut header = PatchHeader::new(self.diff_algorithm.id(), self.crc.clone());
+        header.checksum_algorithm = self.checksum_algorithm.id();
+        header.target_crc = self.target_crc(new_data);
+        header
+    }
+
+    /// Passes the records of the patch to the new data to `write`, the data is changed when every record is written
+    fn write_records<E>(
+        &mut self,
+        new_data: &[IndexedData],
+        target_crc: Vec<u8>,
+        mut write: impl FnMut(Vec<u8>) -> Result<(), E>,
+    ) -> Result<(), E> {
+        // only references are sorted, the new data is cloned once into the state after the records are written
         let new_data = Self::get_sorted(new_data);
-        let new_indexes: Vec<u8> = new_data.iter().map(|x| x.index).collect();
-        // add the crc
-        let mut diff_data: Vec<u8> = vec![self.crc.len() as u8];
-        diff_data.extend(self.crc.clone());
-
-        for data in new_data {
-            if let Some(old_data) = self.data_collection.get_mut(&data.index) {
-                let last_diff = DataDifference::diff(&old_data.data, &data.data);
-                old_data.data = data.data.to_owned();
-
-                // create the diff data for the index
-                let mut new_diff = Vec::new();
-                // set the index
-                new_diff.push(b'v');
-                new_diff.push(data.index);
-                for diff in last_diff.iter() {
-                    let bytes = diff.to_bytes();
-                    // add the difference
-                    new_diff.extend(Difference::get_usize_type_to_bytes(bytes.len()));
-                    new_diff.extend(bytes);
-                }
-                // only add the diff if there are any changes to the data (first 2 bytes are the index)
-                if new_diff.len() > 2 {
-                    diff_data.extend(new_diff);
+        let new_indexes: Vec<Index> = new_data.iter().map(|x| x.index).collect();
+
+        // the sources are searched in the old data
+        let value_hashes = self.get_value_hashes();
+        let copy_sources: Vec<Option<Index>> = new_data
+            .iter()
+            .map(|data| self.find_copy_source(&value_hashes, data))
+            .collect();
+
+        for (data, copy_source) in new_data.iter().zip(copy_sources) {
+            let old_data = self.data_collection.get(&data.index).map(|x| x.data.as_slice());
+            if let Some(source_index) = copy_source {
+                // the new data is the same as the old data of the source, so no differences are needed
+                write(copy_record(data.index, source_index, &[]))?;
+                write(self.removed_record(RECORD_OLD_ENTRY, data.index, old_data, None))?;
+            } else if let Some(old_data) = old_data {
+                let last_diff = self.diff_algorithm.diff(old_data, &data.data);
+
+                // only add the diff if there are any changes to the data
+                if !last_diff.is_empty() {
+                    write(diff_record(RECORD_ENTRY, data.index, &last_diff))?;
+                    write(self.removed_record(RECORD_OLD_ENTRY, data.index, Some(old_data), Some(&last_diff)))?;
                 }
             } else {
                 // add the new data entry
-                self.data_collection.insert(data.index, data.clone());
-                // create the diff data for the index
-                let mut new_diff = Vec::new();
-                // set the index
-                new_diff.push(b'v');
-                new_diff.push(data.index);
-                for diff in DataDifference::diff(&Vec::new(), &data.data).iter() {
-                    let bytes = diff.to_bytes();
-                    // add the difference
-                    new_diff.extend(Difference::get_usize_type_to_bytes(bytes.len()));
-                    new_diff.extend(bytes);
-                }
-                diff_data.extend(new_diff);
+                let diffs = self.diff_algorithm.diff(&Vec::new(), &data.data);
+                write(diff_record(RECORD_ENTRY, data.index, &diffs))?;
+                write(self.removed_record(RECORD_OLD_ENTRY, data.index, None, None))?;
             }
         }
 
         // check if there are indexes removed
-        let src_indexes: Vec<u8> = self.data_collection.keys().copied().collect();
-        let removed_indexes: Vec<u8> = src_indexes
-            .iter()
-            .filter(|x| !new_indexes.contains(x))
-            .copied()
-            .collect();
-
-        for index in &removed_indexes {
-            // remove the index from the data collection
-            self.data_collection.remove(index);
-
+        for (index, old_data) in self.data_collection.iter().filter(|(x, _)| !new_indexes.contains(x)) {
             // add the remove index command to the patch
-            diff_data.extend(vec![b'v', index.to_owned(), b'r']);
+            write(index_record(RECORD_REMOVE_ENTRY, *index))?;
+            write(self.removed_record(RECORD_OLD_ENTRY, *index, Some(&old_data.data), None))?;
         }
 
-        // apply changes in the index mapping to the last index mapping
+        // the changes in the index mapping are applied to the last index mapping after the patch is written
         for (index, new_data) in self.index_mapping.iter() {
-            // ignore mappings for removed indexes
-            if removed_indexes.contains(index) {
-                continue;
-            }
-            // create the diff data for the index
-            // set the index and the control byte
-            let new_diff = vec![b'v', *index, b'm'];
-
-            let mut is_new_mapping = false;
-            // add the diff data for the index mapping to the patch
-            if let Some(old_value) = self.last_index_mapping.get(index) {
-                let last_diff = DataDifference::diff(&old_value.current, new_data);
-
-                let mut diff_only = Vec::new();
-                for diff in last_diff.iter() {
-                    let bytes = diff.to_bytes();
-                    // add the difference
-                    diff_only.extend(Difference::get_usize_type_to_bytes(bytes.len()));
-                    diff_only.extend(bytes);
+            let old_value = self.last_index_mapping.get(index);
+            let Some(new_data) = new_data else {
+                // only mappings which are known by the receiver are removed
+                if let Some(old_value) = old_value {
+                    write(index_record(RECORD_REMOVE_MAP_NAME, *index))?;
+                    write(self.removed_record(RECORD_OLD_MAP_NAME, *index, Some(&old_value.current), None))?;
                 }
-                // only add the diff if there are any changes to the data (first 2 bytes are the index)
-                if !diff_only.is_empty() {
-                    diff_data.extend(new_diff);
-                    diff_data.extend(Difference::get_usize_type_to_bytes(diff_only.len()));
-                    diff_data.extend(diff_only);
-                }
-            } else {
-                is_new_mapping = true;
-                // add the new data entry
-                let mut diff_only = Vec::new();
-                for diff in DataDifference::diff(&Vec::new(), new_data).iter() {
-                    let bytes = diff.to_bytes();
-                    // add the difference
-                    diff_only.extend(Difference::get_usize_type_to_bytes(bytes.len()));
-                    diff_only.extend(bytes);
+                continue;
+            };
+
+            // add the diff data for the index mapping to the patch (also for removed indexes, the mappings are independent of the data)
+            if let Some(old_value) = old_value {
+                let last_diff = self.diff_algorithm.diff(&old_value.current, new_data);
+                // only add the diff if there are any changes to the mapping
+                if !last_diff.is_empty() {
+                    write(diff_record(RECORD_MAP_NAME, *index, &last_diff))?;
+                    write(self.removed_record(RECORD_OLD_MAP_NAME, *index, Some(&old_value.current), Some(&last_diff)))?;
                 }
-                diff_data.extend(new_diff);
-                diff_data.extend(Difference::get_usize_type_to_bytes(diff_only.len()));
-                diff_data.extend(diff_only);
-            }
-            // update the last index mapping
-            if is_new_mapping {
-                self.last_index_mapping
-                    .insert(*index, HistoryValue::new(new_data.clone()));
             } else {
-                self.last_index_mapping
-                    .get_mut(index)
-                    .unwrap()
-                    .set(new_data.clone());
+                let diffs = self.diff_algorithm.diff(&Vec::new(), new_data);
+                write(diff_record(RECORD_MAP_NAME, *index, &diffs))?;
+                write(self.removed_record(RECORD_OLD_MAP_NAME, *index, None, None))?;
             }
         }
-        self.index_mapping.clear();
 
-        diff_data
+        self.data_collection = new_data.into_iter().map(|data| (data.index, data.clone())).collect();
+        for (index, map_name) in std::mem::take(&mut self.index_mapping) {
+            self.set_last_index_mapping(index, map_name);
+        }
+        // the crc of the patched state is the source crc of the next patch
+        self.crc = target_crc;
+        Ok(())
+    }
+
+    /// Record with the bytes which are removed by the differences or the whole old value (empty if the patches are not reversible)
+    fn removed_record(&self, tag: u8, index: Index, old_value: Option<&[u8]>, diffs: Option<&[Difference]>) -> Vec<u8> {
+        if !self.reversible {
+            return Vec::new();
+        }
+        let values = match (old_value, diffs) {
+            (Some(old_value), Some(diffs)) => DataDifference::removed_bytes(old_value, diffs).unwrap_or_default(),
+            (Some(old_value), None) => vec![old_value.to_vec()],
+            (None, _) => Vec::new(),
+        };
+        removed_record(tag, index, &RemovedValue::new(old_value.is_some(), values))
+    }
+
+    /// Indexes of the data by the hash of the data
+    fn get_value_hashes(&self) -> HashMap<u64, Vec<Index>> {
+        let mut hashes: HashMap<u64, Vec<Index>> = HashMap::new();
+        for (index, data) in self.data_collection.iter() {
+            hashes.entry(Self::value_hash(&data.data)).or_default().push(*index);
+        }
+        hashes
+    }
+
+    fn value_hash(data: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Other index which has the new data as old data, if the data of the index itself changed
+    fn find_copy_source(&self, value_hashes: &HashMap<u64, Vec<Index>>, data: &IndexedData) -> Option<Index> {
+        if data.data.is_empty() {
+            return None;
+        }
+        if self.data_collection.get(&data.index).is_some_and(|old| old.data == data.data) {
+            return None;
+        }
+        value_hashes
+            .get(&Self::value_hash(&data.data))?
+            .iter()
+            .find(|index| **index != data.index && self.data_collection[*index].data == data.data)
+            .copied()
     }
 
+    /// Apply the patch to the data
+    ///
+    ///
+    /// The patch is decoded and applied before any data is changed, on an error the data and index mappings stay untouched.<br/>
+    /// If the state after the patch does not match the target CRC of the patch, the changes are rolled back
+    /// and [`SDDEError::TargetMismatch`] is returned.
     pub fn apply_patch(&mut self, diff_data: &[u8]) -> Result<Vec<IndexedDataResult>, SDDEError> {
-        let crc_length = diff_data[0];
-        let crc_value = &diff_data[1..(1 + crc_length as usize)];
-        let bytes = Self::fold_indexed_data(
-            self.data_collection
-                .values()
-                .map(|x| x.to_owned())
-                .collect::<Vec<IndexedData>>()
-                .as_slice(),
-        );
-        let crc = DispnetHash::create(HashType::CRC, &bytes, None);
-        if crc.digest_value != crc_value {
+        let (header, _) = PatchHeader::from_bytes(diff_data)?;
+        let checksum_algorithm = ChecksumAlgorithm::from_id(header.checksum_algorithm)
+            .ok_or(SDDEError::UnknownChecksumAlgorithm { id: header.checksum_algorithm })?;
+        // the CRC of version 0 patches is created from the concatenated data without the index mappings
+        let crc = if header.version == 0 {
+            checksum_algorithm.checksum(&Self::fold_indexed_data(&self.data_collection.values().cloned().collect::<Vec<IndexedData>>()))
+        } else {
+            self.state_checksum(checksum_algorithm)
+        };
+        if crc != header.crc {
             return Err(SDDEError::CRC("CRC value does not match".to_owned()));
         }
 
+        let diff_algorithm = self.get_diff_algorithm(header.diff_algorithm)?;
+        let diffs = Self::get_differences(diff_data)?;
+        // new values of the data entries (None for removed entries) and index mappings
+        let mut data_changes: Vec<(Index, Option<Vec<u8>>)> = Vec::new();
+        let mut map_changes: Vec<(Index, Option<Vec<u8>>)> = Vec::new();
         let mut return_data: Vec<IndexedDataResult> = Vec::new();
-        let diffs = Self::get_differences(diff_data);
         for (index, diff) in diffs.iter() {
-            // the entry should be removed
-            if diff.remove_entry {
-                self.data_collection.remove(index);
-                continue;
+            if diff.map_name_removed {
+                map_changes.push((*index, None));
             }
-            if let Some(src_data) = self.data_collection.get_mut(index) {
-                let data = DataDifference::apply_diff(&src_data.data, &diff.diffs);
-                src_data.data = data;
+            let data = if diff.data_changed {
+                // if the index does not exist (or is removed by the patch), a new data entry is added
+                let src_data = if let Some(source_index) = diff.copy_from {
+                    self.data_collection
+                        .get(&source_index)
+                        .map(|x| x.data.as_slice())
+                        .ok_or(SDDEError::UnknownSourceIndex { index: source_index })?
+                } else if diff.remove_entry {
+                    &[]
+                } else {
+                    self.data_collection
+                        .get(index)
+                        .map(|x| x.data.as_slice())
+                        .unwrap_or_default()
+                };
+                let data = Self::apply_entry_diff(&diff_algorithm, *index, src_data, &diff.diffs)?;
+                data_changes.push((*index, Some(data.clone())));
+                Some(data)
+            } else if diff.remove_entry {
+                // the entry should be removed, the index mapping is kept
+                data_changes.push((*index, None));
+                None
             } else {
-                // the index does not exist, add a new data entry
-                let new_data = Vec::new();
-                let data = DataDifference::apply_diff(&new_data, &diff.diffs);
-                let data = IndexedData::new(*index, data);
-                self.data_collection.insert(*index, data);
-            }
+                self.data_collection.get(index).map(|x| x.data.clone())
+            };
 
-            let mut index_data = IndexedDataResult::new(self.data_collection.get(index).unwrap());
+            // the index mapping of an index without data is changed without a result, except for removed mappings
+            let mut index_data = IndexedDataResult::new(&IndexedData::new(*index, data.clone().unwrap_or_default()));
+            index_data.map_name_removed = diff.map_name_removed;
+            index_data.entry_removed = data.is_none() && diff.remove_entry;
             // check if the map name has changes
-            if diff.map_name_changed.is_some() {
+            if let Some(map_name_changed) = diff.map_name_changed.as_ref() {
+                // a mapping which is removed and changed by the patch is created again
                 let last_index_map_bytes = self
                     .last_index_mapping
                     .get(index)
-                    .cloned()
-                    .unwrap_or_default()
-                    .current;
-                let map_diffs_bytes = DataDifference::apply_diff(
-                    &last_index_map_bytes,
-                    diff.map_name_changed.as_ref().unwrap(),
-                );
+                    .filter(|_| !diff.map_name_removed)
+                    .map(|x| x.current.as_slice())
+                    .unwrap_or_default();
+                let map_diffs_bytes = Self::apply_entry_diff(
+                    &diff_algorithm,
+                    *index,
+                    last_index_map_bytes,
+                    map_name_changed,
+                )?;
                 index_data.map_name_changed = Some(map_diffs_bytes.clone());
-                // update the last index mapping
-                if !self.last_index_mapping.contains_key(index) {
-                    self.last_index_mapping
-                        .insert(*index, HistoryValue::new(map_diffs_bytes));
-                } else {
-                    self.last_index_mapping
-                        .get_mut(index)
-                        .unwrap()
-                        .set(map_diffs_bytes);
-                }
+                map_changes.push((*index, Some(map_diffs_bytes)));
+            }
+            if data.is_some() || diff.map_name_removed {
+                return_data.push(index_data);
             }
-            return_data.push(index_data);
         }
 
-        self.crc = crc.digest_value.clone();
+        // previous values to roll back the changes
+        let data_backup: Vec<(Index, Option<IndexedData>)> = data_changes
+            .iter()
+            .map(|(index, _)| (*index, self.data_collection.get(index).cloned()))
+            .collect();
+        let map_backup: Vec<(Index, Option<HistoryValue>)> = map_changes
+            .iter()
+            .map(|(index, _)| (*index, self.last_index_mapping.get(index).cloned()))
+            .collect();
+
+        for (index, data) in data_changes {
+            if let Some(data) = data {
+                self.data_collection.insert(index, IndexedData::new(index, data));
+            } else {
+                self.data_collection.remove(&index);
+            }
+        }
+        // update the last index mapping
+        for (index, map_name) in map_changes {
+            self.set_last_index_mapping(index, map_name);
+        }
+
+        // patches without a target crc (version 0) are not verified
+        if !header.target_crc.is_empty() {
+            let target_crc = self.state_checksum(checksum_algorithm);
+            if target_crc != header.target_crc {
+                self.roll_back(data_backup, map_backup);
+                return Err(SDDEError::TargetMismatch {
+                    expected: header.target_crc,
+                    actual: target_crc,
+                });
+            }
+        }
+
+        self.update_crc();
 
         Ok(return_data)
     }
 
+    /// Restores the previous values in reverse order, so the oldest value of an index is restored last
+    fn roll_back(&mut self, data_backup: Vec<(Index, Option<IndexedData>)>, map_backup: Vec<(Index, Option<HistoryValue>)>) {
+        for (index, data) in data_backup.into_iter().rev() {
+            if let Some(data) = data {
+                self.data_collection.insert(index, data);
+            } else {
+                self.data_collection.remove(&index);
+            }
+        }
+        for (index, value) in map_backup.into_iter().rev() {
+            if let Some(value) = value {
+                self.last_index_mapping.insert(index, value);
+            } else {
+                self.last_index_mapping.remove(&index);
+            }
+        }
+    }
+
+    /// Sets the current name of the index mapping (`None` removes the mapping)
+    fn set_last_index_mapping(&mut self, index: Index, map_name: Option<Vec<u8>>) {
+        let Some(map_name) = map_name else {
+            self.last_index_mapping.remove(&index);
+            return;
+        };
+        if let Some(value) = self.last_index_mapping.get_mut(&index) {
+            value.set(map_name);
+        } else {
+            self.last_index_mapping.insert(index, HistoryValue::new(map_name));
+        }
+    }
+
+    /// Algorithm for the id of a patch, the own algorithm is preferred so custom algorithms can be applied
+    fn get_diff_algorithm(&self, id: u8) -> Result<Arc<dyn DiffAlgorithm>, SDDEError> {
+        if self.diff_algorithm.id() == id {
+            return Ok(self.diff_algorithm.clone());
+        }
+        diff_algorithm_from_id(id).ok_or(SDDEError::UnknownDiffAlgorithm { id })
+    }
+
+    fn apply_entry_diff(
+        diff_algorithm: &Arc<dyn DiffAlgorithm>,
+        index: Index,
+        data: &[u8],
+        diffs: &[Difference],
+    ) -> Result<Vec<u8>, SDDEError> {
+        diff_algorithm.apply(data, diffs).map_err(|err| match err {
+            SDDEError::RangeOutOfBounds { range, .. } => SDDEError::RangeOutOfBounds {
+                index: Some(index),
+                range,
+            },
+            err => err,
+        })
+    }
+
     pub fn fold_bytes(bytes: &[Vec<u8>]) -> Vec<u8> {
         bytes.iter().fold(Vec::new(), |mut acc, byte| {
             acc.extend(byte.clone());
@@ -386,81 +834,166 @@ impl SimpleDirectDeltaEncoding {
         })
     }
 
-    pub fn get_differences(diff_bytes: &[u8]) -> BTreeMap<u8, EntryDifference> {
-        Self::on_get_differences(diff_bytes, true)
+    pub fn get_differences(diff_bytes: &[u8]) -> Result<BTreeMap<Index, EntryDifference>, SDDEError> {
+        let mut cursor = ByteCursor::new(diff_bytes);
+        let header = PatchHeader::read(&mut cursor)?;
+        if header.version == 0 {
+            return Self::on_get_legacy_differences(&mut cursor);
+        }
+        Self::on_get_differences(&mut cursor)
     }
 
-    pub fn get_index_mapping(&self) -> BTreeMap<u8, HistoryValue> {
+    pub fn get_index_mapping(&self) -> BTreeMap<Index, HistoryValue> {
         self.last_index_mapping.clone()
     }
 
-    fn on_get_differences(diff_bytes: &[u8], has_crc: bool) -> BTreeMap<u8, EntryDifference> {
-        let diff_bytes = if has_crc {
-            Self::get_differences_bytes_with_crc(diff_bytes)
+    fn on_get_differences(cursor: &mut ByteCursor) -> Result<BTreeMap<Index, EntryDifference>, SDDEError> {
+        let mut diffs: BTreeMap<Index, EntryDifference> = BTreeMap::new();
+        while !cursor.is_empty() {
+            let (tag, index) = Self::read_record_start(cursor)?;
+            Self::read_record(cursor, tag, diffs.entry(index).or_default())?;
+        }
+        Ok(diffs)
+    }
+
+    /// Reads the control byte and the index of a record
+    pub(crate) fn read_record_start(cursor: &mut impl PatchSource) -> Result<(u8, Index), SDDEError> {
+        let tag_offset = cursor.offset();
+        let tag = cursor.read_u8()?;
+        if ![
+            RECORD_ENTRY,
+            RECORD_COPY_ENTRY,
+            RECORD_REMOVE_ENTRY,
+            RECORD_MAP_NAME,
+            RECORD_REMOVE_MAP_NAME,
+            RECORD_OLD_ENTRY,
+            RECORD_OLD_MAP_NAME,
+        ]
+        .contains(&tag)
+        {
+            return Err(SDDEError::UnknownControlByte {
+                byte: tag,
+                offset: tag_offset,
+            });
+        }
+        Ok((tag, cursor.read_index()?))
+    }
+
+    /// Reads the payload of a record into the entry of the index
+    pub(crate) fn read_record(cursor: &mut impl PatchSource, tag: u8, entry: &mut EntryDifference) -> Result<(), SDDEError> {
+        // handle remove entry
+        if tag == RECORD_REMOVE_ENTRY {
+            entry.remove_entry = true;
+            return Ok(());
+        }
+        if tag == RECORD_REMOVE_MAP_NAME {
+            entry.map_name_removed = true;
+            return Ok(());
+        }
+        if tag == RECORD_OLD_ENTRY || tag == RECORD_OLD_MAP_NAME {
+            let removed = Self::read_removed_value(cursor)?;
+            if tag == RECORD_OLD_ENTRY {
+                entry.old_data = Some(removed);
+            } else {
+                entry.old_map_name = Some(removed);
+            }
+            return Ok(());
+        }
+
+        let copy_from = if tag == RECORD_COPY_ENTRY {
+            Some(cursor.read_index()?)
         } else {
-            diff_bytes
+            None
         };
-        let mut diffs: BTreeMap<u8, EntryDifference> = BTreeMap::new();
-        let mut i = 0;
+        let diff_count = cursor.read_varint()?;
+        let mut record_diffs = Vec::new();
+        for _ in 0..diff_count {
+            record_diffs.push(Difference::read(cursor)?);
+        }
+
+        if tag == RECORD_MAP_NAME {
+            entry.map_name_changed = Some(record_diffs);
+        } else {
+            entry.data_changed = true;
+            entry.copy_from = copy_from;
+            entry.diffs.extend(record_diffs);
+        }
+        Ok(())
+    }
+
+    fn read_removed_value(cursor: &mut impl PatchSource) -> Result<RemovedValue, SDDEError> {
+        let offset = cursor.offset();
+        let existed = match cursor.read_u8()? {
+            0 => false,
+            1 => true,
+            byte => return Err(SDDEError::UnknownControlByte { byte, offset }),
+        };
+        let count = cursor.read_varint()?;
+        let mut values = Vec::new();
+        for _ in 0..count {
+            let length = cursor.read_varint()?;
+            values.push(cursor.read_vec(length)?);
+        }
+        Ok(RemovedValue::new(existed, values))
+    }
+
+    /// Differences of a version 0 patch (the cursor is positioned after the CRC)
+    pub(crate) fn on_get_legacy_differences(cursor: &mut ByteCursor) -> Result<BTreeMap<Index, EntryDifference>, SDDEError> {
+        let mut diffs: BTreeMap<Index, EntryDifference> = BTreeMap::new();
         let mut index = 0;
-        while i < diff_bytes.len() {
+        while !cursor.is_empty() {
             // get index
-            if diff_bytes[i] == b'v' {
-                index = diff_bytes[i + 1];
-                i += 2;
+            if cursor.peek() == Some(b'v') {
+                cursor.read_u8()?;
+                // the version 0 format has a single byte index
+                index = cursor.read_u8()? as Index;
+                // every index of a version 0 patch is a data entry
+                diffs.entry(index).or_default().data_changed = true;
             }
             // handle remove entry
-            if diff_bytes[i] == b'r' {
-                i += 1;
+            if cursor.peek() == Some(b'r') {
+                cursor.read_u8()?;
                 diffs.insert(index, EntryDifference::remove_entry());
                 continue;
             }
             // handle map name changes
-            if diff_bytes[i] == b'm' {
-                i += 1;
-                let map_diffs_length = Difference::get_usize_type_from_bytes(&diff_bytes[i..]);
-                i += map_diffs_length.1;
-                let bytes = &diff_bytes[i..(i + map_diffs_length.0)];
-                i += bytes.len();
+            if cursor.peek() == Some(b'm') {
+                cursor.read_u8()?;
+                let map_diffs_length = Difference::read_usize_type(cursor)?;
+                let mut map_cursor = cursor.sub_cursor(map_diffs_length)?;
 
                 // get the diffs for the map name
-                let map_diffs_result = Self::on_get_differences(bytes, false);
-                let map_entry_diff = map_diffs_result.get(&0);
-                if map_entry_diff.is_none() {
+                let mut map_entry_diff = Vec::new();
+                while !map_cursor.is_empty() {
+                    map_entry_diff.push(Self::read_legacy_difference(&mut map_cursor)?);
+                }
+                if map_entry_diff.is_empty() {
                     continue;
                 }
-                let map_entry_diff = map_entry_diff.unwrap().to_owned().diffs.clone();
 
                 // insert the map name changed diff
-                if let Some(d) = diffs.get_mut(&index) {
-                    d.map_name_changed = Some(map_entry_diff);
-                } else {
-                    diffs.insert(index, EntryDifference::new(Vec::new()));
-                    diffs.get_mut(&index).unwrap().map_name_changed = Some(map_entry_diff);
-                }
-
+                diffs.entry(index).or_default().map_name_changed = Some(map_entry_diff);
                 continue;
             }
-
-            let diff_length = Difference::get_usize_type_from_bytes(&diff_bytes[i..]);
-            i += diff_length.1;
-            let bytes = &diff_bytes[i..(i + diff_length.0)];
-            let diff = Difference::from_bytes(bytes);
-            i += diff_length.0;
-
-            if let Some(d) = diffs.get_mut(&index) {
-                d.diffs.push(diff);
-            } else {
-                diffs.insert(index, EntryDifference::new(vec![diff]));
+            if cursor.is_empty() {
+                break;
             }
+
+            let diff = Self::read_legacy_difference(cursor)?;
+            diffs.entry(index).or_default().diffs.push(diff);
         }
-        diffs
+        Ok(diffs)
+    }
+
+    /// Reads a length prefixed difference of a version 0 patch
+    fn read_legacy_difference(cursor: &mut ByteCursor) -> Result<Difference, SDDEError> {
+        let diff_length = Difference::read_usize_type(cursor)?;
+        Difference::read_legacy(&mut cursor.sub_cursor(diff_length)?)
     }
 
-    pub fn get_differences_bytes_with_crc(diff_bytes: &[u8]) -> &[u8] {
-        let crc_length = diff_bytes[0];
-        let _crc_value = &diff_bytes[1..(1 + crc_length as usize)];
-        &diff_bytes[(1 + crc_length as usize)..]
+    pub fn get_differences_bytes_with_crc(diff_bytes: &[u8]) -> Result<&[u8], SDDEError> {
+        let (_, header_length) = PatchHeader::from_bytes(diff_bytes)?;
+        Ok(&diff_bytes[header_length..])
     }
 
     fn fold_indexed_data(data: &[IndexedData]) -> Vec<u8> {
@@ -470,9 +1003,9 @@ impl SimpleDirectDeltaEncoding {
         })
     }
 
-    fn get_sorted(data: &[IndexedData]) -> Vec<IndexedData> {
-        let mut data = data.to_vec();
-        data.sort_by(|a, b| a.index.cmp(&b.index));
+    fn get_sorted(data: &[IndexedData]) -> Vec<&IndexedData> {
+        let mut data: Vec<&IndexedData> = data.iter().collect();
+        data.sort_by_key(|a| a.index);
         data
     }
 }
@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::data_difference::{DataDifference, Range};
+use crate::{Index, IndexedData, SDDEError, SimpleDirectDeltaEncoding};
+
+/// Change of two concurrent patches which can not be merged, the change of the first patch is kept
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MergeConflict {
+    /// Both patches changed the same bytes of the data (the ranges are positions in the base data)
+    Range { index: Index, range_a: Range, range_b: Range },
+    /// One patch removed the entry while the other patch changed it, or both patches added different data
+    Entry { index: Index },
+    /// Both patches changed the index mapping of the index to different names (`None` for a removed mapping)
+    MapName {
+        index: Index,
+        name_a: Option<Vec<u8>>,
+        name_b: Option<Vec<u8>>,
+    },
+    /// Both patches changed the index mapping of different indexes to the same name
+    DuplicateMapName { name: Vec<u8>, index_a: Index, index_b: Index },
+}
+
+#[derive(Debug, Clone)]
+pub struct MergeResult {
+    /// Patch for the state after the first patch with the changes of the second patch which do not conflict
+    pub patch: Vec<u8>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Changed part of the base data between two common segments
+#[derive(Debug, PartialEq)]
+struct Hunk {
+    range: Range,
+    value: Vec<u8>,
+}
+
+impl Hunk {
+    fn end(&self) -> usize {
+        self.range.start + self.range.length
+    }
+
+    /// Overlapping ranges and changes at the same position (e.g. two inserts) can not be merged
+    fn overlaps(&self, other: &Hunk) -> bool {
+        (self.range.start < other.end() && other.range.start < self.end()) || self.range.start == other.range.start
+    }
+}
+
+/// Three-way merge of two patches which were created for the same base state
+///
+///
+/// The changes of both patches are compared per index, changes of different bytes of the same index are merged.<br/>
+/// The resulting patch is applied after the first patch and contains the changes of the second patch which do not conflict
+/// (the diff algorithm of the base is used).
+pub fn merge(base: &SimpleDirectDeltaEncoding, patch_a: &[u8], patch_b: &[u8]) -> Result<MergeResult, SDDEError> {
+    let mut state_a = base.clone();
+    state_a.index_mapping.clear();
+    state_a.apply_patch(patch_a)?;
+    let mut state_b = base.clone();
+    state_b.apply_patch(patch_b)?;
+
+    let mut conflicts: Vec<MergeConflict> = Vec::new();
+    let data = |encoding: &SimpleDirectDeltaEncoding| -> BTreeMap<Index, Vec<u8>> {
+        encoding.data_collection.iter().map(|(index, x)| (*index, x.data.clone())).collect()
+    };
+    let names = |encoding: &SimpleDirectDeltaEncoding| -> BTreeMap<Index, Vec<u8>> {
+        encoding.last_index_mapping.iter().map(|(index, x)| (*index, x.current.clone())).collect()
+    };
+    let (base_data, data_a, data_b) = (data(base), data(&state_a), data(&state_b));
+
+    let mut merged_data: Vec<IndexedData> = Vec::new();
+    for index in union_keys(&[&base_data, &data_a, &data_b]) {
+        let (base_value, a, b) = (base_data.get(&index), data_a.get(&index), data_b.get(&index));
+        let merged = match (base_value, a, b) {
+            _ if a == b || b == base_value => a.cloned(),
+            _ if a == base_value => b.cloned(),
+            (Some(base_value), Some(a), Some(b)) => Some(merge_data(index, base_value, a, b, &mut conflicts)),
+            _ => {
+                conflicts.push(MergeConflict::Entry { index });
+                a.cloned()
+            },
+        };
+        if let Some(merged) = merged {
+            merged_data.push(IndexedData::new(index, merged));
+        }
+    }
+
+    let (base_names, names_a, names_b) = (names(base), names(&state_a), names(&state_b));
+    let mut merged_names: BTreeMap<Index, Vec<u8>> = BTreeMap::new();
+    for index in union_keys(&[&base_names, &names_a, &names_b]) {
+        let (base_name, a, b) = (base_names.get(&index), names_a.get(&index), names_b.get(&index));
+        let merged = if a == b || b == base_name {
+            a
+        } else if a == base_name {
+            // a name which the first patch uses for another index is not taken
+            let duplicate = b.and_then(|b| names_a.iter().find(|(i, name)| **i != index && *name == b));
+            if let Some((index_a, name)) = duplicate {
+                conflicts.push(MergeConflict::DuplicateMapName {
+                    name: name.clone(),
+                    index_a: *index_a,
+                    index_b: index,
+                });
+                a
+            } else {
+                b
+            }
+        } else {
+            conflicts.push(MergeConflict::MapName {
+                index,
+                name_a: a.cloned(),
+                name_b: b.cloned(),
+            });
+            a
+        };
+        if let Some(merged) = merged {
+            merged_names.insert(index, merged.clone());
+        }
+    }
+
+    for (index, name) in merged_names.iter() {
+        if names_a.get(index) != Some(name) {
+            state_a.change_index_mapping(*index, name);
+        }
+    }
+    for index in names_a.keys().filter(|index| !merged_names.contains_key(index)) {
+        state_a.remove_index_mapping(*index);
+    }
+    let patch = state_a.patch(&merged_data);
+    Ok(MergeResult { patch, conflicts })
+}
+
+fn union_keys(maps: &[&BTreeMap<Index, Vec<u8>>]) -> BTreeSet<Index> {
+    maps.iter().flat_map(|map| map.keys().copied()).collect()
+}
+
+/// Changed parts of the base data in the new data
+fn hunks(base: &[u8], new: &[u8]) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let (mut base_position, mut new_position) = (0, 0);
+    let segments = DataDifference::common_segments(base, new);
+    for (base_start, new_start, length) in segments.into_iter().chain([(base.len(), new.len(), 0)]) {
+        if base_start > base_position || new_start > new_position {
+            hunks.push(Hunk {
+                range: Range::new(base_position, base_start - base_position),
+                value: new[new_position..new_start].to_vec(),
+            });
+        }
+        base_position = base_start + length;
+        new_position = new_start + length;
+    }
+    hunks
+}
+
+/// Base data with the changes of both values, the changes of the second value which overlap a change of the first value are conflicts
+fn merge_data(index: Index, base: &[u8], a: &[u8], b: &[u8], conflicts: &mut Vec<MergeConflict>) -> Vec<u8> {
+    let hunks_a = hunks(base, a);
+    let mut accepted: Vec<Hunk> = Vec::new();
+    for hunk_b in hunks(base, b) {
+        match hunks_a.iter().find(|hunk_a| hunk_a.overlaps(&hunk_b)) {
+            Some(hunk_a) if *hunk_a != hunk_b => conflicts.push(MergeConflict::Range {
+                index,
+                range_a: hunk_a.range.clone(),
+                range_b: hunk_b.range,
+            }),
+            Some(_) => {},
+            None => accepted.push(hunk_b),
+        }
+    }
+    accepted.extend(hunks_a);
+    accepted.sort_by_key(|hunk| hunk.range.start);
+
+    let mut merged: Vec<u8> = Vec::with_capacity(a.len().max(b.len()));
+    let mut position = 0;
+    for hunk in accepted {
+        merged.extend(&base[position..hunk.range.start]);
+        position = hunk.end();
+        merged.extend(hunk.value);
+    }
+    merged.extend(&base[position..]);
+    merged
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod merge {
+    use crate::*;
+
+    fn base() -> SimpleDirectDeltaEncoding {
+        let mut base = SimpleDirectDeltaEncoding::new(&[
+            IndexedData::new(0, "The quick brown fox jumps over the lazy dog".as_bytes().to_vec()),
+            IndexedData::new(1, "Test 1".as_bytes().to_vec()),
+            IndexedData::new(2, "Test 2".as_bytes().to_vec()),
+        ]);
+        base.change_index_mapping(0, "text".as_bytes());
+        base.change_index_mapping(1, "first".as_bytes());
+        base.apply_index_mappings();
+        base
+    }
+
+    /// Patch of a client with the base state
+    fn client_patch(new_data: &[(Index, &str)], mappings: &[(Index, &str)]) -> Vec<u8> {
+        let mut client = base();
+        for (index, name) in mappings {
+            client.change_index_mapping(*index, name.as_bytes());
+        }
+        let new_data: Vec<IndexedData> = new_data
+            .iter()
+            .map(|(index, data)| IndexedData::new(*index, data.as_bytes().to_vec()))
+            .collect();
+        client.patch(&new_data)
+    }
+
+    /// Applies the first patch and the merged patch to the base
+    fn merged_state(patch_a: &[u8], result: &MergeResult) -> SimpleDirectDeltaEncoding {
+        let mut receiver = base();
+        assert!(receiver.apply_patch(patch_a).is_ok());
+        let applied = receiver.apply_patch(&result.patch);
+        assert!(applied.is_ok(), "{:?}", applied);
+        receiver
+    }
+
+    fn data(encoding: &SimpleDirectDeltaEncoding, index: Index) -> Option<&str> {
+        encoding
+            .data_collection
+            .get(&index)
+            .map(|x| std::str::from_utf8(&x.data).unwrap())
+    }
+
+    #[test]
+    fn merge_different_indexes() {
+        let text = "The quick brown fox jumps over the lazy dog";
+        let patch_a = client_patch(&[(0, text), (1, "Test A"), (2, "Test 2")], &[]);
+        let patch_b = client_patch(&[(0, text), (1, "Test 1")], &[(0, "content")]);
+        let result = merge(&base(), &patch_a, &patch_b).unwrap();
+        assert!(result.conflicts.is_empty());
+
+        let merged = merged_state(&patch_a, &result);
+        assert_eq!(data(&merged, 1), Some("Test A"));
+        assert_eq!(data(&merged, 2), None);
+        assert_eq!(merged.get_index_mapping().get(&0).unwrap().current, "content".as_bytes());
+    }
+
+    #[test]
+    fn merge_ranges_of_same_index() {
+        let patch_a = client_patch(
+            &[(0, "The quick red fox jumps over the lazy dog"), (1, "Test 1"), (2, "Test 2")],
+            &[],
+        );
+        let patch_b = client_patch(
+            &[(0, "The quick brown fox jumps over the sleepy dog!"), (1, "Test 1"), (2, "Test 2")],
+            &[],
+        );
+        let result = merge(&base(), &patch_a, &patch_b).unwrap();
+        assert!(result.conflicts.is_empty(), "{:?}", result.conflicts);
+        let merged = merged_state(&patch_a, &result);
+        assert_eq!(data(&merged, 0), Some("The quick red fox jumps over the sleepy dog!"));
+    }
+
+    #[test]
+    fn merge_overlapping_ranges() {
+        let patch_a = client_patch(
+            &[(0, "The quick red fox jumps over the lazy dog"), (1, "Test 1"), (2, "Test 2")],
+            &[],
+        );
+        let patch_b = client_patch(
+            &[(0, "The quick green fox jumps over the lazy cat"), (1, "Test 1"), (2, "Test 2")],
+            &[],
+        );
+        let result = merge(&base(), &patch_a, &patch_b).unwrap();
+        // the changed words can be split into several ranges
+        assert!(!result.conflicts.is_empty());
+        assert!(result.conflicts.iter().all(|x| matches!(x, MergeConflict::Range { index: 0, .. })));
+        // the change of the first patch is kept, the other change of the second patch is merged
+        let merged = merged_state(&patch_a, &result);
+        assert_eq!(data(&merged, 0), Some("The quick red fox jumps over the lazy cat"));
+    }
+
+    #[test]
+    fn merge_removed_and_changed_entry() {
+        let text = "The quick brown fox jumps over the lazy dog";
+        let patch_a = client_patch(&[(0, text), (2, "Test 2")], &[]);
+        let patch_b = client_patch(&[(0, text), (1, "Test B"), (2, "Test 2")], &[]);
+        let result = merge(&base(), &patch_a, &patch_b).unwrap();
+        assert_eq!(result.conflicts, vec![MergeConflict::Entry { index: 1 }]);
+        assert_eq!(data(&merged_state(&patch_a, &result), 1), None);
+    }
+
+    #[test]
+    fn merge_conflicting_renames() {
+        let text = "The quick brown fox jumps over the lazy dog";
+        let new_data = [(0, text), (1, "Test 1"), (2, "Test 2")];
+        let patch_a = client_patch(&new_data, &[(1, "one"), (2, "second")]);
+        let patch_b = client_patch(&new_data, &[(1, "uno"), (0, "second")]);
+        let result = merge(&base(), &patch_a, &patch_b).unwrap();
+        assert_eq!(
+            result.conflicts,
+            vec![
+                MergeConflict::DuplicateMapName {
+                    name: "second".as_bytes().to_vec(),
+                    index_a: 2,
+                    index_b: 0,
+                },
+                MergeConflict::MapName {
+                    index: 1,
+                    name_a: Some("one".as_bytes().to_vec()),
+                    name_b: Some("uno".as_bytes().to_vec()),
+                },
+            ]
+        );
+        let merged = merged_state(&patch_a, &result);
+        assert_eq!(merged.get_index_mapping().get(&0).unwrap().current, "text".as_bytes());
+        assert_eq!(merged.get_index_mapping().get(&1).unwrap().current, "one".as_bytes());
+    }
+
+    #[test]
+    fn merge_patch_for_other_state() {
+        let patch_a = client_patch(&[(0, "changed")], &[]);
+        let mut other = SimpleDirectDeltaEncoding::new(&[IndexedData::new(0, "other".as_bytes().to_vec())]);
+        let patch_b = other.patch(&[IndexedData::new(0, "changed".as_bytes().to_vec())]);
+        assert!(matches!(merge(&base(), &patch_a, &patch_b), Err(SDDEError::CRC(_))));
+    }
+}
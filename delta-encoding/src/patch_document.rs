@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data_difference::{Difference, DifferenceAction, Range};
+use crate::inspect::{to_hex, PatchInspector, RecordOperation};
+use crate::patch_format::{
+    copy_record, diff_record, index_record, removed_record, PatchHeader, RECORD_ENTRY, RECORD_MAP_NAME,
+    RECORD_OLD_ENTRY, RECORD_OLD_MAP_NAME, RECORD_REMOVE_ENTRY, RECORD_REMOVE_MAP_NAME,
+};
+use crate::{Index, RemovedValue, SDDEError};
+
+/// Human-readable representation of a patch (e.g. as json), converted from and to the binary patch without changes
+///
+///
+/// The records keep the order of the binary patch, so [`Self::to_patch`] returns the same bytes as the converted patch.<br/>
+/// Only versioned patches can be converted (version 0 patches return [`SDDEError::UnsupportedVersion`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchDocument {
+    pub version: u8,
+    pub diff_algorithm: u8,
+    pub checksum_algorithm: u8,
+    /// CRC as hex string
+    pub crc: String,
+    /// Target CRC as hex string (empty if the patch has no target CRC)
+    pub target_crc: String,
+    pub records: Vec<PatchRecord>,
+}
+
+/// Record of a patch, see [`crate::SimpleDirectDeltaEncoding::patch`] for the binary format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum PatchRecord {
+    Entry { index: Index, differences: Vec<PatchDifference> },
+    CopyEntry { index: Index, source_index: Index, differences: Vec<PatchDifference> },
+    RemoveEntry { index: Index },
+    MapName { index: Index, differences: Vec<PatchDifference> },
+    RemoveMapName { index: Index },
+    OldEntry { index: Index, existed: bool, values: Vec<PatchBytes> },
+    OldMapName { index: Index, existed: bool, values: Vec<PatchBytes> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchDifference {
+    pub action: DifferenceAction,
+    pub start: usize,
+    pub length: usize,
+    /// New bytes of the Replace and Insert actions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<PatchBytes>,
+}
+
+/// Bytes as text if they are valid UTF-8, otherwise as array of numbers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PatchBytes {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl PatchBytes {
+    pub fn new(bytes: Vec<u8>) -> PatchBytes {
+        match String::from_utf8(bytes) {
+            Ok(text) => PatchBytes::Text(text),
+            Err(error) => PatchBytes::Bytes(error.into_bytes()),
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            PatchBytes::Text(text) => text.into_bytes(),
+            PatchBytes::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+impl PatchDifference {
+    fn new(diff: Difference) -> PatchDifference {
+        let value = diff.action.has_value().then(|| PatchBytes::new(diff.value));
+        PatchDifference {
+            action: diff.action,
+            start: diff.range.start,
+            length: diff.range.length,
+            value,
+        }
+    }
+
+    /// The value has to be present with the length of the range for the Replace and Insert actions and missing for the others
+    fn to_difference(&self) -> Result<Difference, SDDEError> {
+        let value = match (self.action.has_value(), self.value.clone()) {
+            (true, Some(value)) => value.into_bytes(),
+            (false, None) => Vec::new(),
+            (true, None) => {
+                return Err(SDDEError::DifferenceInvalid(format!("Missing value of the {:?} action", self.action)))
+            },
+            (false, Some(_)) => {
+                return Err(SDDEError::DifferenceInvalid(format!("The {:?} action has no value", self.action)))
+            },
+        };
+        if self.action.has_value() && value.len() != self.length {
+            return Err(SDDEError::DifferenceInvalid(format!(
+                "The value length {} is not the length {} of the {:?} action",
+                value.len(),
+                self.length,
+                self.action
+            )));
+        }
+        Ok(Difference {
+            action: self.action,
+            range: Range::new(self.start, self.length),
+            value,
+            is_open: false,
+        })
+    }
+}
+
+impl PatchDocument {
+    pub fn from_patch(patch: &[u8]) -> Result<PatchDocument, SDDEError> {
+        let inspector = PatchInspector::new(patch)?;
+        let records = inspector
+            .records
+            .into_iter()
+            .map(|record| {
+                let index = record.index;
+                match record.operation {
+                    RecordOperation::Entry { diffs } => PatchRecord::Entry {
+                        index,
+                        differences: from_differences(diffs),
+                    },
+                    RecordOperation::CopyEntry { source_index, diffs } => PatchRecord::CopyEntry {
+                        index,
+                        source_index,
+                        differences: from_differences(diffs),
+                    },
+                    RecordOperation::RemoveEntry => PatchRecord::RemoveEntry { index },
+                    RecordOperation::MapName { diffs } => PatchRecord::MapName {
+                        index,
+                        differences: from_differences(diffs),
+                    },
+                    RecordOperation::RemoveMapName => PatchRecord::RemoveMapName { index },
+                    RecordOperation::OldEntry(removed) => PatchRecord::OldEntry {
+                        index,
+                        existed: removed.existed,
+                        values: removed.values.into_iter().map(PatchBytes::new).collect(),
+                    },
+                    RecordOperation::OldMapName(removed) => PatchRecord::OldMapName {
+                        index,
+                        existed: removed.existed,
+                        values: removed.values.into_iter().map(PatchBytes::new).collect(),
+                    },
+                }
+            })
+            .collect();
+
+        let header = inspector.header;
+        Ok(PatchDocument {
+            version: header.version,
+            diff_algorithm: header.diff_algorithm,
+            checksum_algorithm: header.checksum_algorithm,
+            crc: to_hex(&header.crc),
+            target_crc: to_hex(&header.target_crc),
+            records,
+        })
+    }
+
+    /// Binary patch which can be applied with [`crate::SimpleDirectDeltaEncoding::apply_patch`]
+    ///
+    ///
+    /// An invalid hex value or a difference value which does not match its action and length returns [`SDDEError::DifferenceInvalid`].
+    pub fn to_patch(&self) -> Result<Vec<u8>, SDDEError> {
+        if self.version == 0 {
+            return Err(SDDEError::UnsupportedVersion { version: 0, offset: 0 });
+        }
+        let header = PatchHeader {
+            version: self.version,
+            diff_algorithm: self.diff_algorithm,
+            checksum_algorithm: self.checksum_algorithm,
+            crc: from_hex(&self.crc)?,
+            target_crc: from_hex(&self.target_crc)?,
+        };
+        let mut bytes = header.to_bytes();
+        for record in self.records.iter() {
+            bytes.extend(match record {
+                PatchRecord::Entry { index, differences } => diff_record(RECORD_ENTRY, *index, &to_differences(differences)?),
+                PatchRecord::CopyEntry {
+                    index,
+                    source_index,
+                    differences,
+                } => copy_record(*index, *source_index, &to_differences(differences)?),
+                PatchRecord::RemoveEntry { index } => index_record(RECORD_REMOVE_ENTRY, *index),
+                PatchRecord::MapName { index, differences } => {
+                    diff_record(RECORD_MAP_NAME, *index, &to_differences(differences)?)
+                },
+                PatchRecord::RemoveMapName { index } => index_record(RECORD_REMOVE_MAP_NAME, *index),
+                PatchRecord::OldEntry { index, existed, values } => {
+                    removed_record(RECORD_OLD_ENTRY, *index, &to_removed_value(*existed, values))
+                },
+                PatchRecord::OldMapName { index, existed, values } => {
+                    removed_record(RECORD_OLD_MAP_NAME, *index, &to_removed_value(*existed, values))
+                },
+            });
+        }
+        Ok(bytes)
+    }
+}
+
+fn from_differences(diffs: Vec<Difference>) -> Vec<PatchDifference> {
+    diffs.into_iter().map(PatchDifference::new).collect()
+}
+
+fn to_differences(differences: &[PatchDifference]) -> Result<Vec<Difference>, SDDEError> {
+    differences.iter().map(|x| x.to_difference()).collect()
+}
+
+fn to_removed_value(existed: bool, values: &[PatchBytes]) -> RemovedValue {
+    RemovedValue::new(existed, values.iter().cloned().map(PatchBytes::into_bytes).collect())
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>, SDDEError> {
+    let invalid = || SDDEError::DifferenceInvalid(format!("Invalid hex value {}", hex));
+    // only digits, a sign is accepted by from_str_radix
+    if !hex.bytes().all(|x| x.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    // an odd length has no pair of hex digits at the end
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|x| u8::from_str_radix(x, 16).ok()).ok_or_else(invalid))
+        .collect()
+}
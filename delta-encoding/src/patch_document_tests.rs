@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod patch_document {
+    use crate::*;
+
+    fn patch(reversible: bool) -> Vec<u8> {
+        let mut sender = SimpleDirectDeltaEncoding::new(&[
+            IndexedData::new(0, "Hello World".as_bytes().to_vec()),
+            IndexedData::new(1, vec![0, 159, 146, 150]),
+            IndexedData::new(2, "Test 2".as_bytes().to_vec()),
+        ]);
+        sender.set_reversible(reversible);
+        sender.set_diff_algorithm(LcsDiff::default());
+        sender.change_index_mapping(0, "greeting".as_bytes());
+        sender.patch(&[
+            IndexedData::new(0, "Hello brave World".as_bytes().to_vec()),
+            IndexedData::new(1, vec![0, 159, 146, 151]),
+            IndexedData::new(3, "Test 2".as_bytes().to_vec()),
+        ])
+    }
+
+    #[test]
+    fn patch_json_round_trip() {
+        for reversible in [false, true] {
+            let patch = patch(reversible);
+            let document = PatchDocument::from_patch(&patch).unwrap();
+            let json = serde_json::to_string_pretty(&document).unwrap();
+            let parsed: PatchDocument = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, document);
+            assert_eq!(parsed.to_patch().unwrap(), patch);
+        }
+    }
+
+    #[test]
+    fn patch_json_is_readable() {
+        let document = PatchDocument::from_patch(&patch(false)).unwrap();
+        let json = serde_json::to_value(&document).unwrap();
+        assert_eq!(json["diff_algorithm"], LcsDiff::ID);
+        assert!(json["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|x| x["record"] == "copy_entry" && x["index"] == 3 && x["source_index"] == 2));
+        let text = serde_json::to_string(&document).unwrap();
+        assert!(text.contains("\"value\":\"brave \""), "{}", text);
+        assert!(text.contains("\"value\":\"greeting\""), "{}", text);
+    }
+
+    #[test]
+    fn changed_patch_json() {
+        let json = r#"{
+            "version": 1, "diff_algorithm": 1, "checksum_algorithm": 0, "crc": "", "target_crc": "",
+            "records": [
+                { "record": "entry", "index": 5, "differences": [{ "action": "Insert", "start": 0, "length": 3, "value": "new" }] },
+                { "record": "map_name", "index": 5, "differences": [{ "action": "Insert", "start": 0, "length": 3, "value": [107, 101, 121] }] }
+            ]
+        }"#;
+        let document: PatchDocument = serde_json::from_str(json).unwrap();
+        let differences = SimpleDirectDeltaEncoding::get_differences(&document.to_patch().unwrap()).unwrap();
+        let entry = differences.get(&5).unwrap();
+        assert_eq!(entry.diffs[0].value, "new".as_bytes());
+        assert_eq!(entry.map_name_changed.as_ref().unwrap()[0].value, "key".as_bytes());
+
+        let mut invalid = document.clone();
+        invalid.crc = "0g".to_owned();
+        assert!(invalid.to_patch().is_err());
+    }
+
+    #[test]
+    fn malformed_patch_json() {
+        let document = |crc: &str, difference: &str| {
+            let json = format!(
+                r#"{{
+                    "version": 1, "diff_algorithm": 1, "checksum_algorithm": 0, "crc": "{}", "target_crc": "",
+                    "records": [{{ "record": "entry", "index": 5, "differences": [{}] }}]
+                }}"#,
+                crc, difference
+            );
+            serde_json::from_str::<PatchDocument>(&json).unwrap()
+        };
+        let valid = r#"{ "action": "Insert", "start": 0, "length": 3, "value": "new" }"#;
+        assert!(document("0a1b", valid).to_patch().is_ok());
+
+        // a sign or an odd length is no hex value
+        for crc in ["+f", "-1", "0a1", "0x"] {
+            assert!(matches!(document(crc, valid).to_patch(), Err(SDDEError::DifferenceInvalid(_))), "{}", crc);
+        }
+        // the value is missing, has a different length than the range or is not allowed for the action
+        for difference in [
+            r#"{ "action": "Insert", "start": 0, "length": 3 }"#,
+            r#"{ "action": "Replace", "start": 0, "length": 3 }"#,
+            r#"{ "action": "Insert", "start": 0, "length": 2, "value": "new" }"#,
+            r#"{ "action": "Replace", "start": 0, "length": 4, "value": [1, 2, 3] }"#,
+            r#"{ "action": "Delete", "start": 0, "length": 3, "value": "new" }"#,
+        ] {
+            let result = document("", difference).to_patch();
+            assert!(matches!(result, Err(SDDEError::DifferenceInvalid(_))), "{}", difference);
+        }
+    }
+
+    #[test]
+    fn serde_public_types() {
+        let data = IndexedData::new(1, "Test".as_bytes().to_vec());
+        let parsed: IndexedData = serde_json::from_str(&serde_json::to_string(&data).unwrap()).unwrap();
+        assert_eq!((parsed.index, parsed.data), (data.index, data.data));
+
+        let differences = SimpleDirectDeltaEncoding::get_differences(&patch(true)).unwrap();
+        let json = serde_json::to_string(&differences).unwrap();
+        let parsed: std::collections::BTreeMap<Index, EntryDifference> = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+
+        let mapping = HistoryValue::new("key".as_bytes().to_vec());
+        let parsed: HistoryValue = serde_json::from_str(&serde_json::to_string(&mapping).unwrap()).unwrap();
+        assert_eq!(parsed.current, mapping.current);
+    }
+}
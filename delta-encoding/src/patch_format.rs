@@ -0,0 +1,342 @@
+use crate::checksum::ChecksumAlgorithm;
+use crate::data_difference::{Difference, DifferenceAction, Range};
+use crate::diff_algorithm::PositionalDiff;
+use crate::{Index, RemovedValue, SDDEError};
+
+/// Magic bytes at the start of every versioned patch (patches without them are read as version 0)
+pub const PATCH_MAGIC: &[u8; 4] = b"SDDE";
+/// Format version written by `SimpleDirectDeltaEncoding::patch`
+pub const PATCH_VERSION: u8 = 1;
+
+/// Record with the data differences of an index: [tag, index, difference count, Difference 1, ...]
+pub const RECORD_ENTRY: u8 = b'e';
+/// Record with the data differences of an index applied to the old data of the source index:<br/>
+/// [tag, index, source index, difference count, Difference 1, ...]
+pub const RECORD_COPY_ENTRY: u8 = b'c';
+/// Record removing the data entry of an index: [tag, index]
+pub const RECORD_REMOVE_ENTRY: u8 = b'r';
+/// Record with the index mapping differences of an index: [tag, index, difference count, Difference 1, ...]
+pub const RECORD_MAP_NAME: u8 = b'm';
+/// Record removing the index mapping of an index: [tag, index]
+pub const RECORD_REMOVE_MAP_NAME: u8 = b'n';
+/// Record of a reversible patch with the data bytes of an index which are removed or replaced:<br/>
+/// [tag, index, existed, value count, Value length 1, Value 1, ...]
+pub const RECORD_OLD_ENTRY: u8 = b'o';
+/// Record of a reversible patch with the index mapping bytes of an index which are removed or replaced:<br/>
+/// [tag, index, existed, value count, Value length 1, Value 1, ...]
+pub const RECORD_OLD_MAP_NAME: u8 = b'k';
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatchHeader {
+    pub version: u8,
+    /// Id of the [`crate::DiffAlgorithm`] which created the differences
+    pub diff_algorithm: u8,
+    /// Id of the [`ChecksumAlgorithm`] of the CRC values
+    pub checksum_algorithm: u8,
+    pub crc: Vec<u8>,
+    /// CRC of the state after the patch is applied (empty if the patch has no target CRC)
+    pub target_crc: Vec<u8>,
+}
+
+impl PatchHeader {
+    pub fn new(diff_algorithm: u8, crc: Vec<u8>) -> PatchHeader {
+        PatchHeader {
+            version: PATCH_VERSION,
+            diff_algorithm,
+            checksum_algorithm: ChecksumAlgorithm::default().id(),
+            crc,
+            target_crc: Vec::new(),
+        }
+    }
+
+    /// Header bytes with the following format:<br/>
+    /// [Magic, Version, Diff algorithm, Checksum algorithm, CRC length, CRC value, Target CRC length, Target CRC value]
+    /// * The Magic is the 4 bytes `SDDE`
+    /// * The Version is a single byte with the format version
+    /// * The Diff algorithm is a single byte with the id of the diff algorithm
+    /// * The Checksum algorithm is a single byte with the id of the checksum algorithm of both CRC values
+    /// * The CRC length and Target CRC length are varints with the length of the CRC values
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = PATCH_MAGIC.to_vec();
+        bytes.push(self.version);
+        bytes.push(self.diff_algorithm);
+        bytes.push(self.checksum_algorithm);
+        bytes.extend(Varint::to_bytes(self.crc.len()));
+        bytes.extend(self.crc.clone());
+        bytes.extend(Varint::to_bytes(self.target_crc.len()));
+        bytes.extend(self.target_crc.clone());
+        bytes
+    }
+
+    /// Returns the header and the bytes count of the header
+    ///
+    ///
+    /// Patches without the magic bytes are version 0 patches which only start with [CRC length, CRC value]
+    pub fn from_bytes(bytes: &[u8]) -> Result<(PatchHeader, usize), SDDEError> {
+        let mut cursor = ByteCursor::new(bytes);
+        let header = Self::read(&mut cursor)?;
+        Ok((header, cursor.offset()))
+    }
+
+    pub(crate) fn read(cursor: &mut ByteCursor) -> Result<PatchHeader, SDDEError> {
+        if !cursor.remaining().starts_with(PATCH_MAGIC) {
+            let crc_length = cursor.read_u8()? as usize;
+            return Ok(PatchHeader {
+                version: 0,
+                diff_algorithm: PositionalDiff::ID,
+                checksum_algorithm: ChecksumAlgorithm::DispnetCrc.id(),
+                crc: cursor.read_bytes(crc_length)?.to_vec(),
+                target_crc: Vec::new(),
+            });
+        }
+        cursor.read_bytes(PATCH_MAGIC.len())?;
+        Self::read_versioned(cursor)
+    }
+
+    /// Header of a versioned patch after the magic bytes
+    pub(crate) fn read_versioned(cursor: &mut impl PatchSource) -> Result<PatchHeader, SDDEError> {
+        let offset = cursor.offset();
+        let version = cursor.read_u8()?;
+        if version > PATCH_VERSION {
+            return Err(SDDEError::UnsupportedVersion { version, offset });
+        }
+        let diff_algorithm = cursor.read_u8()?;
+        let checksum_algorithm = cursor.read_u8()?;
+        let crc_length = cursor.read_varint()?;
+        let crc = cursor.read_vec(crc_length)?;
+        let target_crc_length = cursor.read_varint()?;
+        Ok(PatchHeader {
+            version,
+            diff_algorithm,
+            checksum_algorithm,
+            crc,
+            target_crc: cursor.read_vec(target_crc_length)?,
+        })
+    }
+}
+
+/// Unsigned LEB128 encoding, 7 bits per byte with the high bit set on every byte except the last
+pub struct Varint {}
+
+impl Varint {
+    pub fn to_bytes(value: usize) -> Vec<u8> {
+        let mut value = value as u64;
+        let mut bytes = Vec::new();
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(byte);
+                return bytes;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    /// Returns the value and the bytes count of the varint
+    pub fn from_bytes(bytes: &[u8]) -> Result<(usize, usize), SDDEError> {
+        let mut cursor = ByteCursor::new(bytes);
+        let value = cursor.read_varint()?;
+        Ok((value, cursor.offset()))
+    }
+}
+
+/// Bytes of a patch which are read in order, a slice ([`ByteCursor`]) or a stream ([`crate::PatchReader`])
+pub(crate) trait PatchSource {
+    /// Position in the whole patch, used for the offsets of the errors
+    fn offset(&self) -> usize;
+
+    fn read_u8(&mut self) -> Result<u8, SDDEError>;
+
+    fn read_vec(&mut self, length: usize) -> Result<Vec<u8>, SDDEError>;
+
+    fn read_varint(&mut self) -> Result<usize, SDDEError> {
+        let mut value: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_u8()?;
+            let bits = (byte & 0x7f) as u64;
+            if shift >= 64 || (shift > 0 && bits >> (64 - shift) != 0) {
+                return Err(SDDEError::LengthOverflow);
+            }
+            value |= bits << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return usize::try_from(value).map_err(|_| SDDEError::LengthOverflow);
+            }
+        }
+    }
+
+    /// Reads a varint index of a record
+    fn read_index(&mut self) -> Result<Index, SDDEError> {
+        Index::try_from(self.read_varint()?).map_err(|_| SDDEError::LengthOverflow)
+    }
+}
+
+/// Read position in a patch, every read is bounds checked and errors contain the offset in the patch
+pub(crate) struct ByteCursor<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> ByteCursor<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> ByteCursor<'a> {
+        ByteCursor {
+            bytes,
+            offset: 0,
+            end: bytes.len(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.offset >= self.end
+    }
+
+    pub(crate) fn remaining(&self) -> &'a [u8] {
+        &self.bytes[self.offset..self.end]
+    }
+
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.remaining().first().copied()
+    }
+
+    pub(crate) fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], SDDEError> {
+        if length > self.end - self.offset {
+            return Err(SDDEError::Truncated { offset: self.end });
+        }
+        let bytes = &self.bytes[self.offset..(self.offset + length)];
+        self.offset += length;
+        Ok(bytes)
+    }
+
+    /// Reads all bytes until the end of the cursor
+    pub(crate) fn read_to_end(&mut self) -> &'a [u8] {
+        let bytes = self.remaining();
+        self.offset = self.end;
+        bytes
+    }
+
+    /// Cursor over the next `length` bytes (offsets stay relative to the whole patch)
+    pub(crate) fn sub_cursor(&mut self, length: usize) -> Result<ByteCursor<'a>, SDDEError> {
+        let start = self.offset;
+        self.read_bytes(length)?;
+        Ok(ByteCursor {
+            bytes: self.bytes,
+            offset: start,
+            end: self.offset,
+        })
+    }
+}
+
+impl PatchSource for ByteCursor<'_> {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn read_u8(&mut self) -> Result<u8, SDDEError> {
+        let byte = self.peek().ok_or(SDDEError::Truncated { offset: self.offset })?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn read_vec(&mut self, length: usize) -> Result<Vec<u8>, SDDEError> {
+        Ok(self.read_bytes(length)?.to_vec())
+    }
+}
+
+/// Record bytes for an index with differences ([`RECORD_ENTRY`] or [`RECORD_MAP_NAME`])
+pub fn diff_record(tag: u8, index: Index, diffs: &[Difference]) -> Vec<u8> {
+    let mut bytes = index_record(tag, index);
+    bytes.extend(Varint::to_bytes(diffs.len()));
+    for diff in diffs {
+        bytes.extend(diff.encode());
+    }
+    bytes
+}
+
+/// Record bytes for an index with differences to the old data of the source index ([`RECORD_COPY_ENTRY`])
+pub fn copy_record(index: Index, source_index: Index, diffs: &[Difference]) -> Vec<u8> {
+    let mut bytes = index_record(RECORD_COPY_ENTRY, index);
+    bytes.extend(Varint::to_bytes(source_index as usize));
+    bytes.extend(Varint::to_bytes(diffs.len()));
+    for diff in diffs {
+        bytes.extend(diff.encode());
+    }
+    bytes
+}
+
+/// Record bytes for the removed bytes of an index ([`RECORD_OLD_ENTRY`] or [`RECORD_OLD_MAP_NAME`])
+pub fn removed_record(tag: u8, index: Index, removed: &RemovedValue) -> Vec<u8> {
+    let mut bytes = index_record(tag, index);
+    bytes.push(removed.existed as u8);
+    bytes.extend(Varint::to_bytes(removed.values.len()));
+    for value in removed.values.iter() {
+        bytes.extend(Varint::to_bytes(value.len()));
+        bytes.extend(value);
+    }
+    bytes
+}
+
+/// Record bytes for an index without a payload ([`RECORD_REMOVE_ENTRY`] or [`RECORD_REMOVE_MAP_NAME`])
+pub fn index_record(tag: u8, index: Index) -> Vec<u8> {
+    let mut bytes = vec![tag];
+    bytes.extend(Varint::to_bytes(index as usize));
+    bytes
+}
+
+impl Difference {
+    /// Difference bytes of the versioned patch format:<br/>
+    /// [Action, Range start, Range length, Value]
+    /// * The Range start and Range length are varints
+    /// * The Value has the Range length and is only present in the Replace and Insert actions
+    /// * The Copy action has the varints [Source start, Source length] instead of a Value
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![self.action.into()];
+        bytes.extend(Varint::to_bytes(self.range.start));
+        bytes.extend(Varint::to_bytes(self.range.length));
+        if let DifferenceAction::Copy { src_start, len } = self.action {
+            bytes.extend(Varint::to_bytes(src_start));
+            bytes.extend(Varint::to_bytes(len));
+        } else if self.action.has_value() {
+            bytes.extend(self.value.to_owned());
+        }
+        bytes
+    }
+
+    /// Returns the difference and the bytes count of the encoded difference
+    pub fn decode(bytes: &[u8]) -> Result<(Self, usize), SDDEError> {
+        let mut cursor = ByteCursor::new(bytes);
+        let diff = Self::read(&mut cursor)?;
+        Ok((diff, cursor.offset()))
+    }
+
+    pub(crate) fn read(cursor: &mut impl PatchSource) -> Result<Self, SDDEError> {
+        let mut action = Self::read_action(cursor)?;
+        let range_start = cursor.read_varint()?;
+        let range_length = cursor.read_varint()?;
+        let mut value = Vec::new();
+        if let DifferenceAction::Copy { .. } = action {
+            action = DifferenceAction::Copy {
+                src_start: cursor.read_varint()?,
+                len: cursor.read_varint()?,
+            };
+        } else if action.has_value() {
+            value = cursor.read_vec(range_length)?;
+        }
+        Ok(Self {
+            action,
+            range: Range::new(range_start, range_length),
+            value,
+            is_open: false,
+        })
+    }
+
+    pub(crate) fn read_action(cursor: &mut impl PatchSource) -> Result<DifferenceAction, SDDEError> {
+        let offset = cursor.offset();
+        let byte = cursor.read_u8()?;
+        byte.try_into()
+            .map_err(|_| SDDEError::UnknownControlByte { byte, offset })
+    }
+}
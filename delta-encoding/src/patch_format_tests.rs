@@ -0,0 +1,304 @@
+#[cfg(test)]
+mod patch_format {
+    use crate::*;
+
+    #[test]
+    fn varint_round_trip() {
+        for value in [0, 1, 105, 108, 109, 114, 115, 118, 127, 128, 255, 300, 65_535, 1 << 35, usize::MAX] {
+            let bytes = Varint::to_bytes(value);
+            assert_eq!(Varint::from_bytes(&bytes).unwrap(), (value, bytes.len()));
+        }
+    }
+
+    #[test]
+    fn varint_bytes() {
+        assert_eq!(Varint::to_bytes(0), vec![0]);
+        assert_eq!(Varint::to_bytes(127), vec![127]);
+        assert_eq!(Varint::to_bytes(128), vec![128, 1]);
+        assert_eq!(Varint::to_bytes(300), vec![172, 2]);
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let header = PatchHeader::new(PositionalDiff::ID, vec![1, 2, 3]);
+        let bytes = header.to_bytes();
+        assert_eq!(bytes, vec![b'S', b'D', b'D', b'E', PATCH_VERSION, PositionalDiff::ID, ChecksumAlgorithm::DispnetCrc.id(), 3, 1, 2, 3, 0]);
+        assert_eq!(PatchHeader::from_bytes(&bytes).unwrap(), (header, bytes.len()));
+    }
+
+    #[test]
+    fn header_round_trip_with_target() {
+        let mut header = PatchHeader::new(PositionalDiff::ID, vec![1, 2, 3]);
+        header.target_crc = vec![4, 5];
+        let bytes = header.to_bytes();
+        assert_eq!(bytes, vec![b'S', b'D', b'D', b'E', PATCH_VERSION, PositionalDiff::ID, ChecksumAlgorithm::DispnetCrc.id(), 3, 1, 2, 3, 2, 4, 5]);
+        assert_eq!(PatchHeader::from_bytes(&bytes).unwrap(), (header, bytes.len()));
+    }
+
+    #[test]
+    fn header_version_0() {
+        let bytes = vec![3, 1, 2, 3, b'v', 0];
+        let (header, length) = PatchHeader::from_bytes(&bytes).unwrap();
+        assert_eq!(header.version, 0);
+        assert_eq!(header.crc, vec![1, 2, 3]);
+        assert_eq!(length, 4);
+    }
+
+    #[test]
+    fn difference_encode_decode() {
+        let diffs = DataDifference::diff("Test1THELLO".as_bytes(), "Test2Thello!!".as_bytes());
+        for diff in diffs {
+            let bytes = diff.encode();
+            let (decoded, length) = Difference::decode(&bytes).unwrap();
+            assert_eq!(length, bytes.len());
+            assert_eq!(decoded.action, diff.action);
+            assert_eq!(decoded.range, diff.range);
+            assert_eq!(decoded.value, diff.value);
+        }
+    }
+
+    #[test]
+    fn copy_difference_encode_decode() {
+        let diff = Difference {
+            action: DifferenceAction::Copy { src_start: 300, len: 20 },
+            range: Range::new(5, 2),
+            value: vec![],
+            is_open: false,
+        };
+        let bytes = diff.encode();
+        assert_eq!(bytes, vec![b'c', 5, 2, 172, 2, 20]);
+        let (decoded, length) = Difference::decode(&bytes).unwrap();
+        assert_eq!(length, bytes.len());
+        assert_eq!(decoded.action, diff.action);
+        assert_eq!(decoded.range, diff.range);
+        assert!(decoded.value.is_empty());
+    }
+
+    #[test]
+    fn legacy_difference_without_copy() {
+        assert!(matches!(Difference::from_bytes(&[b'c', b':', 1, b'-', 1]), Err(SDDEError::UnknownControlByte { byte: b'c', offset: 0 })));
+    }
+
+    #[test]
+    fn usize_type_prefix_values() {
+        for value in [105, 108, 115] {
+            let bytes = Difference::get_usize_type_to_bytes(value);
+            assert_eq!(Difference::get_usize_type_from_bytes(&bytes).unwrap(), (value, bytes.len()));
+        }
+    }
+
+    #[test]
+    fn apply_patch_with_control_byte_lengths() {
+        // difference lengths and values which were misread as control bytes in the version 0 format
+        for length in [105, 108, 109, 114, 115, 118] {
+            let src = vec![IndexedData::new(0, vec![0; 200]), IndexedData::new(118, vec![1; 10])];
+            let mut sdd = SimpleDirectDeltaEncoding::new(&src);
+            sdd.change_index_mapping(109, &vec![b'm'; length]);
+            let new_data = &[
+                IndexedData::new(0, [vec![0; 10], vec![b'v'; length], vec![0; 200]].concat()),
+                IndexedData::new(109, vec![b'r'; length]),
+            ];
+            let patch_data = sdd.patch(new_data);
+
+            let mut sdd2 = SimpleDirectDeltaEncoding::new(&src);
+            let result = sdd2.apply_patch(&patch_data);
+            assert!(result.is_ok());
+            assert_eq!(
+                sdd.data_collection.values().map(|x| x.data.clone()).collect::<Vec<_>>(),
+                sdd2.data_collection.values().map(|x| x.data.clone()).collect::<Vec<_>>(),
+            );
+            assert_eq!(
+                sdd.get_index_mapping().get(&109).unwrap().current,
+                sdd2.get_index_mapping().get(&109).unwrap().current
+            );
+        }
+    }
+
+    #[test]
+    fn apply_patch_version_0() {
+        // patch created with the version 0 format
+        let patch_data = vec![10, 49, 51, 54, 55, 54, 57, 54, 57, 55, 49, 118, 0, 6, 105, 58, 4, 45, 1, 50];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&[IndexedData::new(0, "Test".as_bytes().to_vec())]);
+        let result = sdd.apply_patch(&patch_data);
+        assert!(result.is_ok());
+        assert_eq!(sdd.data_collection.get(&0).unwrap().data, "Test2".as_bytes());
+    }
+
+    #[test]
+    fn apply_patch_version_0_index_mapping() {
+        // patch created with the version 0 format which renames `name` to `firstname`
+        let patch_data = vec![10, 50, 54, 48, 48, 53, 49, 52, 53, 55, 55, 118, 1, 109, 21, 9, 114, 58, 0, 45, 4, 102, 105, 114, 115, 10, 105, 58, 4, 45, 5, 116, 110, 97, 109, 101];
+        let props = vec![
+            IndexedData::new(0, "30".as_bytes().to_vec()),
+            IndexedData::new(1, "\"John\"".as_bytes().to_vec()),
+        ];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        sdd.change_index_mapping(0, "age".as_bytes());
+        sdd.change_index_mapping(1, "name".as_bytes());
+        sdd.apply_index_mappings();
+        let result = sdd.apply_patch(&patch_data);
+        assert!(result.is_ok());
+        assert_eq!(sdd.get_index_mapping().get(&1).unwrap().current, "firstname".as_bytes());
+    }
+
+    fn sample_patch() -> (Vec<IndexedData>, Vec<u8>) {
+        let props = vec![
+            IndexedData::new(0, "Test".as_bytes().to_vec()),
+            IndexedData::new(1, "Test2".as_bytes().to_vec()),
+            IndexedData::new(2, "Test3".as_bytes().to_vec()),
+        ];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        sdd.change_index_mapping(0, "t1".as_bytes());
+        let new_data = &[
+            IndexedData::new(0, "Test1".as_bytes().to_vec()),
+            IndexedData::new(1, "Tes".as_bytes().to_vec()),
+            IndexedData::new(3, "Test4".as_bytes().to_vec()),
+        ];
+        (props, sdd.patch(new_data))
+    }
+
+    #[test]
+    fn apply_truncated_patch() {
+        let (props, patch_data) = sample_patch();
+        for length in 0..patch_data.len() {
+            let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+            let result = sdd.apply_patch(&patch_data[..length]);
+            // a cut between two records is detected by the target crc
+            let err = result.unwrap_err();
+            assert!(matches!(err, SDDEError::Truncated { .. } | SDDEError::CRC(_) | SDDEError::TargetMismatch { .. }), "{:?}", err);
+            assert_eq!(SimpleDirectDeltaEncoding::fold_index(&sdd.data_collection.values().cloned().collect::<Vec<_>>()), "TestTest2Test3".as_bytes());
+            assert!(sdd.get_index_mapping().is_empty());
+        }
+    }
+
+    #[test]
+    fn apply_patch_target_mismatch() {
+        let (props, mut patch_data) = sample_patch();
+        // the last byte is the value of the index mapping "t1"
+        *patch_data.last_mut().unwrap() = b'2';
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        let result = sdd.apply_patch(&patch_data);
+        assert!(matches!(result, Err(SDDEError::TargetMismatch { .. })));
+        assert_eq!(SimpleDirectDeltaEncoding::fold_index(&sdd.data_collection.values().cloned().collect::<Vec<_>>()), "TestTest2Test3".as_bytes());
+        assert!(sdd.get_index_mapping().is_empty());
+    }
+
+    #[test]
+    fn apply_corrupted_patch() {
+        let (props, patch_data) = sample_patch();
+        let (_, header_length) = PatchHeader::from_bytes(&patch_data).unwrap();
+        for offset in header_length..patch_data.len() {
+            for byte in [0, 1, b'e', b'r', b'm', b'i', 127, 128, 255] {
+                let mut corrupted = patch_data.clone();
+                corrupted[offset] = byte;
+                let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+                if sdd.apply_patch(&corrupted).is_err() {
+                    assert_eq!(SimpleDirectDeltaEncoding::fold_index(&sdd.data_collection.values().cloned().collect::<Vec<_>>()), "TestTest2Test3".as_bytes());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn apply_corrupted_patch_version_0() {
+        let patch_data = vec![10, 50, 54, 48, 48, 53, 49, 52, 53, 55, 55, 118, 1, 109, 21, 9, 114, 58, 0, 45, 4, 102, 105, 114, 115, 10, 105, 58, 4, 45, 5, 116, 110, 97, 109, 101];
+        let props = vec![
+            IndexedData::new(0, "30".as_bytes().to_vec()),
+            IndexedData::new(1, "\"John\"".as_bytes().to_vec()),
+        ];
+        for length in 0..patch_data.len() {
+            let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+            _ = sdd.apply_patch(&patch_data[..length]);
+            for byte in [0, b'v', b'r', b'm', b's', b'l', 255] {
+                let mut corrupted = patch_data.clone();
+                corrupted[length] = byte;
+                let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+                _ = sdd.apply_patch(&corrupted);
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_record() {
+        let mut patch_data = PatchHeader::new(PositionalDiff::ID, vec![1]).to_bytes();
+        let offset = patch_data.len();
+        patch_data.extend([b'x', 0]);
+        let result = SimpleDirectDeltaEncoding::get_differences(&patch_data);
+        assert!(matches!(result, Err(SDDEError::UnknownControlByte { byte: b'x', offset: o }) if o == offset));
+    }
+
+    #[test]
+    fn unknown_action() {
+        let mut patch_data = PatchHeader::new(PositionalDiff::ID, vec![1]).to_bytes();
+        let offset = patch_data.len() + 3;
+        patch_data.extend([RECORD_ENTRY, 0, 1, b'x', 0, 0]);
+        let result = SimpleDirectDeltaEncoding::get_differences(&patch_data);
+        assert!(matches!(result, Err(SDDEError::UnknownControlByte { byte: b'x', offset: o }) if o == offset));
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut patch_data = PatchHeader::new(PositionalDiff::ID, vec![1]).to_bytes();
+        patch_data[PATCH_MAGIC.len()] = PATCH_VERSION + 1;
+        let result = SimpleDirectDeltaEncoding::get_differences(&patch_data);
+        assert!(matches!(result, Err(SDDEError::UnsupportedVersion { .. })));
+    }
+
+    #[test]
+    fn varint_overflow() {
+        assert!(matches!(Varint::from_bytes(&[255; 11]), Err(SDDEError::LengthOverflow)));
+        assert!(matches!(Varint::from_bytes(&[255, 255, 255, 255, 255, 255, 255, 255, 255, 2]), Err(SDDEError::LengthOverflow)));
+        assert!(matches!(Varint::from_bytes(&[128, 128]), Err(SDDEError::Truncated { offset: 2 })));
+    }
+
+    #[test]
+    fn huge_value_length() {
+        let mut patch_data = PatchHeader::new(PositionalDiff::ID, vec![1]).to_bytes();
+        patch_data.extend([RECORD_ENTRY, 0, 1, b'i', 0]);
+        patch_data.extend(Varint::to_bytes(usize::MAX));
+        let result = SimpleDirectDeltaEncoding::get_differences(&patch_data);
+        assert!(matches!(result, Err(SDDEError::Truncated { .. })));
+    }
+
+    #[test]
+    fn apply_range_out_of_bounds() {
+        let props = vec![IndexedData::new(4, "Test".as_bytes().to_vec())];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        let mut patch_data = PatchHeader::new(PositionalDiff::ID, sdd.crc.clone()).to_bytes();
+        patch_data.extend(diff_record(RECORD_ENTRY, 4, &[Difference {
+            action: DifferenceAction::Delete,
+            range: Range::new(2, 10),
+            value: vec![],
+            is_open: false,
+        }]));
+        let result = sdd.apply_patch(&patch_data);
+        assert!(matches!(result, Err(SDDEError::RangeOutOfBounds { index: Some(4), range }) if range == Range::new(2, 10)));
+        assert_eq!(sdd.data_collection.get(&4).unwrap().data, "Test".as_bytes());
+    }
+
+    #[test]
+    fn apply_copy_unknown_source() {
+        let props = vec![IndexedData::new(0, "Test".as_bytes().to_vec())];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        let mut patch_data = PatchHeader::new(PositionalDiff::ID, sdd.crc.clone()).to_bytes();
+        patch_data.extend(copy_record(1, 2, &[]));
+        let result = sdd.apply_patch(&patch_data);
+        assert!(matches!(result, Err(SDDEError::UnknownSourceIndex { index: 2 })));
+        assert!(!sdd.data_collection.contains_key(&1));
+    }
+
+    #[test]
+    fn index_overflow() {
+        let mut patch_data = PatchHeader::new(PositionalDiff::ID, vec![1]).to_bytes();
+        patch_data.extend([RECORD_REMOVE_ENTRY]);
+        patch_data.extend(Varint::to_bytes(Index::MAX as usize + 1));
+        let result = SimpleDirectDeltaEncoding::get_differences(&patch_data);
+        assert!(matches!(result, Err(SDDEError::LengthOverflow)));
+    }
+
+    #[test]
+    fn legacy_difference_invalid_separator() {
+        assert!(matches!(Difference::from_bytes(&[b'r', b'x', 1, b'-', 1, 2]), Err(SDDEError::UnknownControlByte { byte: b'x', offset: 1 })));
+        assert!(matches!(Difference::from_bytes(b"r:"), Err(SDDEError::Truncated { offset: 2 })));
+    }
+}
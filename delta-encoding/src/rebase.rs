@@ -0,0 +1,201 @@
+use crate::compose::{difference, ComposedValue, Edit, Piece};
+use crate::data_difference::{Difference, DifferenceAction};
+use crate::diff_algorithm::diff_algorithm_from_id;
+use crate::patch_format::{
+    copy_record, diff_record, index_record, PatchHeader, PATCH_VERSION, RECORD_ENTRY, RECORD_MAP_NAME,
+    RECORD_REMOVE_ENTRY, RECORD_REMOVE_MAP_NAME,
+};
+use crate::{Index, SDDEError, SimpleDirectDeltaEncoding};
+
+/// Patch with the changes of the patch for the state after the other patch (operational transform)
+///
+///
+/// Both patches have to be created for the same state, the ranges of the differences are moved by the changes of the other patch:
+/// * Bytes which both patches insert at the same position are placed after the bytes of the other patch
+/// * Bytes which both patches delete or replace are only deleted once, the bytes inserted by the other patch are kept
+/// * Changes of an entry or index mapping which the other patch removes or replaces as a whole are dropped
+///
+/// The rebased patch has no target CRC, the state after both patches is not known without the data.<br/>
+/// Only patches of the built-in diff algorithms with the same checksum algorithm can be rebased.
+pub fn rebase(patch: &[u8], onto: &[u8]) -> Result<Vec<u8>, SDDEError> {
+    let (header, _) = PatchHeader::from_bytes(patch)?;
+    let (onto_header, _) = PatchHeader::from_bytes(onto)?;
+    if header.version == 0 || onto_header.version == 0 {
+        return Err(SDDEError::NotRebasable("Version 0 patches have no target CRC".to_owned()));
+    }
+    if header.checksum_algorithm != onto_header.checksum_algorithm || header.crc != onto_header.crc {
+        return Err(SDDEError::NotRebasable("The patches are not created for the same state".to_owned()));
+    }
+    for header in [&header, &onto_header] {
+        if diff_algorithm_from_id(header.diff_algorithm).is_none() {
+            return Err(SDDEError::UnknownDiffAlgorithm { id: header.diff_algorithm });
+        }
+    }
+
+    let onto_diffs = SimpleDirectDeltaEncoding::get_differences(onto)?;
+    let mut records: Vec<u8> = Vec::new();
+    for (index, diff) in SimpleDirectDeltaEncoding::get_differences(patch)? {
+        let other = onto_diffs.get(&index);
+        if diff.data_changed {
+            if let Some(source_index) = diff.copy_from {
+                // the copy refers to the data of the source index after the other patch
+                if onto_diffs.get(&source_index).is_some_and(|x| x.data_changed || x.remove_entry) {
+                    return Err(SDDEError::NotRebasable(format!(
+                        "The source index {} of the copy to the index {} is changed by the other patch",
+                        source_index, index
+                    )));
+                }
+                records.extend(copy_record(index, source_index, &diff.diffs));
+            } else if diff.remove_entry {
+                records.extend(index_record(RECORD_REMOVE_ENTRY, index));
+                records.extend(diff_record(RECORD_ENTRY, index, &diff.diffs));
+            } else if let Some(other) = other.filter(|x| x.data_changed || x.remove_entry) {
+                // a value which the other patch replaces as a whole has no positions to move the changes to
+                if !other.remove_entry && other.copy_from.is_none() {
+                    let diffs = rebase_differences(index, &diff.diffs, &other.diffs)?;
+                    records.extend(diff_record(RECORD_ENTRY, index, &diffs));
+                }
+            } else {
+                records.extend(diff_record(RECORD_ENTRY, index, &diff.diffs));
+            }
+        } else if diff.remove_entry {
+            records.extend(index_record(RECORD_REMOVE_ENTRY, index));
+        }
+
+        if diff.map_name_removed {
+            records.extend(index_record(RECORD_REMOVE_MAP_NAME, index));
+            if let Some(map_name_changed) = diff.map_name_changed.as_ref() {
+                records.extend(diff_record(RECORD_MAP_NAME, index, map_name_changed));
+            }
+        } else if let Some(map_name_changed) = diff.map_name_changed.as_ref() {
+            match other {
+                Some(other) if other.map_name_removed => {},
+                Some(other) if other.map_name_changed.is_some() => {
+                    let other_map_name = other.map_name_changed.as_deref().unwrap_or_default();
+                    let diffs = rebase_differences(index, map_name_changed, other_map_name)?;
+                    records.extend(diff_record(RECORD_MAP_NAME, index, &diffs));
+                },
+                _ => records.extend(diff_record(RECORD_MAP_NAME, index, map_name_changed)),
+            }
+        }
+    }
+
+    let rebased_header = PatchHeader {
+        version: PATCH_VERSION,
+        diff_algorithm: header.diff_algorithm,
+        checksum_algorithm: header.checksum_algorithm,
+        crc: onto_header.target_crc,
+        target_crc: Vec::new(),
+    };
+    Ok([rebased_header.to_bytes(), records].concat())
+}
+
+/// Edits of the differences applied to a value with unknown data
+fn edits(index: Index, diffs: &[Difference]) -> Result<Vec<Edit>, SDDEError> {
+    let mut value = ComposedValue::unchanged();
+    value.apply(index, diffs)?;
+    Ok(value.edits())
+}
+
+/// Differences for the value after the other differences with the same changes of the old bytes
+fn rebase_differences(index: Index, diffs: &[Difference], other_diffs: &[Difference]) -> Result<Vec<Difference>, SDDEError> {
+    let other_edits = edits(index, other_diffs)?;
+    let mut differences: Vec<Difference> = Vec::new();
+    // bytes inserted and deleted by the rebased differences so far
+    let (mut added, mut removed): (usize, usize) = (0, 0);
+    for edit in edits(index, diffs)? {
+        let out_of_bounds = || SDDEError::RangeOutOfBounds {
+            index: Some(index),
+            range: edit.range.clone(),
+        };
+        let mut position = map_position(&other_edits, edit.range.start)
+            .and_then(|position| shift(position, added, removed))
+            .ok_or_else(out_of_bounds)?;
+        for piece in edit.pieces.iter() {
+            let length = piece.length();
+            differences.push(match piece {
+                Piece::Literal(value) => difference(DifferenceAction::Insert, position, length, value.clone()),
+                Piece::Base { start, length } => {
+                    let src_start = map_source(&other_edits, *start, *length).ok_or_else(|| {
+                        SDDEError::NotRebasable(format!("The copied bytes of the index {} are changed by the other patch", index))
+                    })?;
+                    difference(DifferenceAction::Copy { src_start, len: *length }, position, 0, Vec::new())
+                },
+            });
+            position = position.checked_add(length).ok_or_else(out_of_bounds)?;
+            added = added.checked_add(length).ok_or_else(out_of_bounds)?;
+        }
+        // only the old bytes which the other differences kept are deleted
+        let end = edit.range.start.checked_add(edit.range.length).ok_or_else(out_of_bounds)?;
+        for (start, end) in kept_ranges(&other_edits, edit.range.start, end) {
+            let position = map_position(&other_edits, start)
+                .and_then(|position| shift(position, added, removed))
+                .ok_or_else(out_of_bounds)?;
+            differences.push(difference(DifferenceAction::Delete, position, end - start, Vec::new()));
+            removed += end - start;
+        }
+    }
+    Ok(differences)
+}
+
+/// Position moved by the inserted and deleted bytes before it (`None` if it overflows)
+fn shift(position: usize, added: usize, removed: usize) -> Option<usize> {
+    position.checked_add(added)?.checked_sub(removed)
+}
+
+/// Position of an old byte in the value after the edits, a byte which the edits replaced is after the new bytes of the edit
+///
+///
+/// Returns `None` if the position overflows.
+fn map_position(edits: &[Edit], position: usize) -> Option<usize> {
+    let (mut added, mut removed): (usize, usize) = (0, 0);
+    for edit in edits {
+        let end = edit.range.start.checked_add(edit.range.length)?;
+        let length = edit.pieces.iter().try_fold(0usize, |sum, x| sum.checked_add(x.length()))?;
+        if end <= position {
+            added = added.checked_add(length)?;
+            removed += edit.range.length;
+        } else if edit.range.start <= position {
+            return shift(edit.range.start, added, removed)?.checked_add(length);
+        } else {
+            break;
+        }
+    }
+    shift(position, added, removed)
+}
+
+/// Position of the old bytes in the value after the edits if the edits did not change or split them
+fn map_source(edits: &[Edit], start: usize, length: usize) -> Option<usize> {
+    let end = start.checked_add(length)?;
+    // an insert at the start or the end does not split the bytes
+    let changed = edits
+        .iter()
+        .any(|edit| edit.range.start < end && edit.range.start.saturating_add(edit.range.length) > start);
+    if changed {
+        return None;
+    }
+    map_position(edits, start)
+}
+
+/// Parts of the old range which are not replaced by the edits
+fn kept_ranges(edits: &[Edit], start: usize, end: usize) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let mut position = start;
+    for edit in edits {
+        let edit_end = edit.range.start.saturating_add(edit.range.length);
+        if edit_end <= position || edit.range.length == 0 {
+            continue;
+        }
+        if edit.range.start >= end {
+            break;
+        }
+        if edit.range.start > position {
+            ranges.push((position, edit.range.start));
+        }
+        position = edit_end;
+    }
+    if position < end {
+        ranges.push((position, end));
+    }
+    ranges
+}
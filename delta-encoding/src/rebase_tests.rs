@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod rebase {
+    use crate::tests::props;
+    use crate::*;
+
+    /// Patch of a client with the base state
+    fn client_patch(changes: &[(Index, Option<&str>)]) -> Vec<u8> {
+        let mut client = SimpleDirectDeltaEncoding::new(&props());
+        client.set_diff_algorithm(LcsDiff::default());
+        let mut new_data = props();
+        for (index, value) in changes {
+            new_data.retain(|x| x.index != *index);
+            if let Some(value) = value {
+                new_data.push(IndexedData::new(*index, value.as_bytes().to_vec()));
+            }
+        }
+        client.patch(&new_data)
+    }
+
+    /// Applies the other patch and the rebased patch to the base
+    fn rebased_state(patch: &[u8], onto: &[u8]) -> SimpleDirectDeltaEncoding {
+        let rebased = rebase(patch, onto).unwrap();
+        let mut receiver = SimpleDirectDeltaEncoding::new(&props());
+        assert!(receiver.apply_patch(onto).is_ok());
+        let applied = receiver.apply_patch(&rebased);
+        assert!(applied.is_ok(), "{:?}", applied);
+        receiver
+    }
+
+    fn data(encoding: &SimpleDirectDeltaEncoding, index: Index) -> Option<&str> {
+        encoding
+            .data_collection
+            .get(&index)
+            .map(|x| std::str::from_utf8(&x.data).unwrap())
+    }
+
+    #[test]
+    fn rebase_inserts_at_different_positions() {
+        let onto = client_patch(&[(0, Some("Oh, Hello World!"))]);
+        let patch = client_patch(&[(0, Some("Hello brave World"))]);
+        assert_eq!(data(&rebased_state(&patch, &onto), 0), Some("Oh, Hello brave World!"));
+        assert_eq!(data(&rebased_state(&onto, &patch), 0), Some("Oh, Hello brave World!"));
+    }
+
+    #[test]
+    fn rebase_inserts_at_same_position() {
+        let onto = client_patch(&[(0, Some("Hello World, A"))]);
+        let patch = client_patch(&[(0, Some("Hello World, B"))]);
+        assert_eq!(data(&rebased_state(&patch, &onto), 0), Some("Hello World, A, B"));
+    }
+
+    #[test]
+    fn rebase_overlapping_deletes() {
+        let onto = client_patch(&[(2, Some("abcdefghijklmnuvwxyz"))]);
+        let patch = client_patch(&[(2, Some("abcdefghijpqrstuvwxyz"))]);
+        assert_eq!(data(&rebased_state(&patch, &onto), 2), Some("abcdefghijuvwxyz"));
+        assert_eq!(data(&rebased_state(&onto, &patch), 2), Some("abcdefghijuvwxyz"));
+    }
+
+    #[test]
+    fn rebase_different_indexes() {
+        let onto = client_patch(&[(1, None), (3, Some("new"))]);
+        let patch = client_patch(&[(0, Some("Hello")), (2, Some("xyz"))]);
+        let state = rebased_state(&patch, &onto);
+        assert_eq!(data(&state, 0), Some("Hello"));
+        assert_eq!(data(&state, 1), None);
+        assert_eq!(data(&state, 2), Some("xyz"));
+        assert_eq!(data(&state, 3), Some("new"));
+    }
+
+    #[test]
+    fn rebase_onto_removed_entry() {
+        let onto = client_patch(&[(1, None)]);
+        let patch = client_patch(&[(1, Some("Test 1 changed"))]);
+        assert_eq!(data(&rebased_state(&patch, &onto), 1), None);
+    }
+
+    #[test]
+    fn rebase_patch_for_other_state() {
+        let onto = client_patch(&[(0, Some("Hello"))]);
+        let mut other = SimpleDirectDeltaEncoding::new(&[IndexedData::new(0, "other".as_bytes().to_vec())]);
+        let patch = other.patch(&[IndexedData::new(0, "changed".as_bytes().to_vec())]);
+        assert!(matches!(rebase(&patch, &onto), Err(SDDEError::NotRebasable(_))));
+    }
+
+    #[test]
+    fn rebase_ranges_out_of_bounds() {
+        let patch = |diff: Difference| {
+            let mut header = PatchHeader::new(PositionalDiff::ID, vec![1]);
+            header.target_crc = vec![2];
+            [header.to_bytes(), diff_record(RECORD_ENTRY, 0, &[diff])].concat()
+        };
+        let delete = patch(Difference {
+            action: DifferenceAction::Delete,
+            range: Range::new(usize::MAX - 5, 5),
+            value: vec![],
+            is_open: false,
+        });
+        let insert = patch(Difference {
+            action: DifferenceAction::Insert,
+            range: Range::new(0, 10),
+            value: "new values".as_bytes().to_vec(),
+            is_open: false,
+        });
+        let result = rebase(&delete, &insert);
+        assert!(matches!(result, Err(SDDEError::RangeOutOfBounds { index: Some(0), .. })), "{:?}", result);
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+
+use crate::checksum::ChecksumAlgorithm;
+use crate::patch_format::{ByteCursor, PatchSource, Varint};
+use crate::{HistoryValue, Index, IndexedData, SDDEError, SimpleDirectDeltaEncoding};
+
+/// Magic bytes at the start of every snapshot
+pub const SNAPSHOT_MAGIC: &[u8; 4] = b"SDDS";
+/// Format version written by [`SimpleDirectDeltaEncoding::to_snapshot`]
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+impl SimpleDirectDeltaEncoding {
+    /// Snapshot of the state which can be persisted and restored with [`Self::from_snapshot`]
+    ///
+    ///
+    /// The snapshot is a byte array with the following format:<br/>
+    /// [Magic, Version, Checksum algorithm, CRC length, CRC value, Entry count, Entry 1, ..., Mapping count, Mapping 1, ..., Changed mapping count, Changed mapping 1, ...]
+    /// * The Magic is the 4 bytes `SDDS`
+    /// * The Version is a single byte with the snapshot format version
+    /// * The Checksum algorithm is a single byte with the id of the checksum algorithm of the CRC value
+    /// * An Entry is [Index, Data length, Data] ordered by the index
+    /// * A Mapping is [Index, Name length, Name, Last name length, Last name] ordered by the index (the applied index mappings with their history)
+    /// * A Changed mapping is [Index, Removed, Name length, Name] ordered by the index (the index mappings for the next patch, Removed is 0 or 1)
+    /// * The counts, indexes and lengths are varints
+    ///
+    ///
+    /// The diff algorithm and the reversible option are settings of the sender and not part of the snapshot.
+    pub fn to_snapshot(&self) -> Vec<u8> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.push(SNAPSHOT_VERSION);
+        bytes.push(self.checksum_algorithm.id());
+        write_value(&mut bytes, &self.crc);
+        bytes.extend(Varint::to_bytes(self.data_collection.len()));
+        for (index, data) in self.data_collection.iter() {
+            bytes.extend(Varint::to_bytes(*index as usize));
+            write_value(&mut bytes, &data.data);
+        }
+        bytes.extend(Varint::to_bytes(self.last_index_mapping.len()));
+        for (index, value) in self.last_index_mapping.iter() {
+            bytes.extend(Varint::to_bytes(*index as usize));
+            write_value(&mut bytes, &value.current);
+            write_value(&mut bytes, &value.last);
+        }
+        bytes.extend(Varint::to_bytes(self.index_mapping.len()));
+        for (index, name) in self.index_mapping.iter() {
+            bytes.extend(Varint::to_bytes(*index as usize));
+            bytes.push(name.is_none() as u8);
+            write_value(&mut bytes, name.as_deref().unwrap_or_default());
+        }
+        bytes
+    }
+
+    /// Restores the state of a snapshot created with [`Self::to_snapshot`]
+    ///
+    ///
+    /// The CRC of the restored state is validated, a snapshot with changed data returns [`SDDEError::CRC`].<br/>
+    /// The restored encoding uses the default diff algorithm.
+    pub fn from_snapshot(snapshot: &[u8]) -> Result<SimpleDirectDeltaEncoding, SDDEError> {
+        let mut cursor = ByteCursor::new(snapshot);
+        let magic = cursor.read_bytes(SNAPSHOT_MAGIC.len())?;
+        if magic != SNAPSHOT_MAGIC {
+            return Err(SDDEError::UnknownControlByte { byte: magic[0], offset: 0 });
+        }
+        let offset = cursor.offset();
+        let version = cursor.read_u8()?;
+        if version > SNAPSHOT_VERSION {
+            return Err(SDDEError::UnsupportedVersion { version, offset });
+        }
+        let id = cursor.read_u8()?;
+        let checksum_algorithm = ChecksumAlgorithm::from_id(id).ok_or(SDDEError::UnknownChecksumAlgorithm { id })?;
+        let crc = read_value(&mut cursor)?;
+
+        let mut data: Vec<IndexedData> = Vec::new();
+        for _ in 0..cursor.read_varint()? {
+            let index = cursor.read_index()?;
+            data.push(IndexedData::new(index, read_value(&mut cursor)?));
+        }
+        let mut last_index_mapping: BTreeMap<Index, HistoryValue> = BTreeMap::new();
+        for _ in 0..cursor.read_varint()? {
+            let index = cursor.read_index()?;
+            let current = read_value(&mut cursor)?;
+            let last = read_value(&mut cursor)?;
+            last_index_mapping.insert(index, HistoryValue { current, last });
+        }
+        let mut index_mapping: BTreeMap<Index, Option<Vec<u8>>> = BTreeMap::new();
+        for _ in 0..cursor.read_varint()? {
+            let index = cursor.read_index()?;
+            let offset = cursor.offset();
+            let removed = match cursor.read_u8()? {
+                0 => false,
+                1 => true,
+                byte => return Err(SDDEError::UnknownControlByte { byte, offset }),
+            };
+            let name = read_value(&mut cursor)?;
+            index_mapping.insert(index, (!removed).then_some(name));
+        }
+        if let Some(byte) = cursor.peek() {
+            return Err(SDDEError::UnknownControlByte {
+                byte,
+                offset: cursor.offset(),
+            });
+        }
+
+        let mut encoding = Self::load(&data, crc);
+        encoding.checksum_algorithm = checksum_algorithm;
+        encoding.last_index_mapping = last_index_mapping;
+        encoding.index_mapping = index_mapping;
+        let actual = encoding.state_checksum(checksum_algorithm);
+        if actual != encoding.crc {
+            return Err(SDDEError::CRC(format!(
+                "Snapshot CRC {:?} does not match the restored state {:?}",
+                encoding.crc, actual
+            )));
+        }
+        Ok(encoding)
+    }
+}
+
+/// Writes a varint length prefixed value
+fn write_value(bytes: &mut Vec<u8>, value: &[u8]) {
+    bytes.extend(Varint::to_bytes(value.len()));
+    bytes.extend(value);
+}
+
+fn read_value(cursor: &mut ByteCursor) -> Result<Vec<u8>, SDDEError> {
+    let length = cursor.read_varint()?;
+    Ok(cursor.read_bytes(length)?.to_vec())
+}
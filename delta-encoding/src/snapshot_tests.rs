@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod snapshot {
+    use crate::*;
+
+    fn encoding() -> SimpleDirectDeltaEncoding {
+        let mut encoding = SimpleDirectDeltaEncoding::new(&[
+            IndexedData::new(0, "Test".as_bytes().to_vec()),
+            IndexedData::new(1, "Test 1".as_bytes().to_vec()),
+            IndexedData::new(300, Vec::new()),
+        ]);
+        encoding.set_checksum_algorithm(ChecksumAlgorithm::Blake3);
+        encoding.change_index_mapping(0, "first".as_bytes());
+        encoding.change_index_mapping(1, "second".as_bytes());
+        encoding.apply_index_mappings();
+        encoding.change_index_mapping(0, "renamed".as_bytes());
+        encoding.apply_index_mappings();
+        // changed mappings for the next patch
+        encoding.change_index_mapping(300, "empty".as_bytes());
+        encoding.remove_index_mapping(1);
+        encoding
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let mut encoding = encoding();
+        let mut restored = SimpleDirectDeltaEncoding::from_snapshot(&encoding.to_snapshot()).unwrap();
+        assert_eq!(restored.crc, encoding.crc);
+        assert_eq!(restored.get_state(), encoding.get_state());
+        let mapping = restored.get_index_mapping();
+        assert_eq!(mapping.get(&0).unwrap().current, "renamed".as_bytes());
+        assert_eq!(mapping.get(&0).unwrap().last, "first".as_bytes());
+        assert_eq!(restored.to_snapshot(), encoding.to_snapshot());
+
+        // the restored instance creates the same patch with the pending mappings
+        let new_data = [
+            IndexedData::new(0, "Test 0".as_bytes().to_vec()),
+            IndexedData::new(300, "300".as_bytes().to_vec()),
+        ];
+        assert_eq!(restored.patch(&new_data), encoding.patch(&new_data));
+        assert!(!restored.get_index_mapping().contains_key(&1));
+    }
+
+    #[test]
+    fn snapshot_of_patched_receiver() {
+        let mut sender = encoding();
+        let mut receiver = SimpleDirectDeltaEncoding::from_snapshot(&sender.to_snapshot()).unwrap();
+        receiver.apply_index_mappings();
+        sender.apply_index_mappings();
+        let patch = sender.patch(&[IndexedData::new(2, "new".as_bytes().to_vec())]);
+
+        let mut restored = SimpleDirectDeltaEncoding::from_snapshot(&receiver.to_snapshot()).unwrap();
+        assert!(restored.apply_patch(&patch).is_ok());
+        assert_eq!(restored.get_state(), sender.get_state());
+    }
+
+    #[test]
+    fn invalid_snapshot() {
+        let snapshot = encoding().to_snapshot();
+        for length in 0..snapshot.len() {
+            assert!(SimpleDirectDeltaEncoding::from_snapshot(&snapshot[..length]).is_err(), "length {}", length);
+        }
+
+        let mut changed = snapshot.clone();
+        let position = changed.windows(6).position(|x| x == "Test 1".as_bytes()).unwrap();
+        changed[position] = b'X';
+        assert!(matches!(SimpleDirectDeltaEncoding::from_snapshot(&changed), Err(SDDEError::CRC(_))));
+
+        let mut newer = snapshot.clone();
+        newer[SNAPSHOT_MAGIC.len()] = SNAPSHOT_VERSION + 1;
+        assert!(matches!(
+            SimpleDirectDeltaEncoding::from_snapshot(&newer),
+            Err(SDDEError::UnsupportedVersion { .. })
+        ));
+
+        let patch = encoding().patch(&[]);
+        assert!(SimpleDirectDeltaEncoding::from_snapshot(&patch).is_err());
+        assert!(SimpleDirectDeltaEncoding::from_snapshot(&[snapshot, vec![0]].concat()).is_err());
+    }
+}
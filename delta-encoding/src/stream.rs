@@ -0,0 +1,173 @@
+use std::io::{BufRead, BufReader, Read, Write};
+
+use crate::patch_format::{PatchHeader, PatchSource, PATCH_MAGIC};
+use crate::{EntryDifference, Index, SDDEError, SimpleDirectDeltaEncoding};
+
+/// Writes a patch record by record, e.g. to a file, so the whole patch is never in memory
+///
+///
+/// The header is written first, the records are the bytes of the record helpers (e.g. [`crate::diff_record`]).<br/>
+/// See [`SimpleDirectDeltaEncoding::patch_to_writer`] to write the patch of new data.
+pub struct PatchWriter<W: Write> {
+    writer: W,
+    bytes_written: usize,
+}
+
+impl<W: Write> PatchWriter<W> {
+    /// Writes the header of the patch
+    pub fn new(writer: W, header: &PatchHeader) -> Result<PatchWriter<W>, SDDEError> {
+        let mut patch_writer = PatchWriter { writer, bytes_written: 0 };
+        patch_writer.write_bytes(&header.to_bytes())?;
+        Ok(patch_writer)
+    }
+
+    pub fn write_record(&mut self, record: &[u8]) -> Result<(), SDDEError> {
+        self.write_bytes(record)
+    }
+
+    /// Bytes count of the header and the records written so far
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+
+    /// Flushes the writer and returns it
+    pub fn finish(mut self) -> Result<W, SDDEError> {
+        self.writer.flush().map_err(|err| SDDEError::Io(err.to_string()))?;
+        Ok(self.writer)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SDDEError> {
+        self.writer.write_all(bytes).map_err(|err| SDDEError::Io(err.to_string()))?;
+        self.bytes_written += bytes.len();
+        Ok(())
+    }
+}
+
+/// Reads a patch from a stream and yields the differences of the indexes one after the other
+///
+///
+/// Consecutive records of the same index are one item, an index can be in more than one item
+/// (e.g. the data and the index mapping changes are separate records in a patch of [`SimpleDirectDeltaEncoding::patch`]).<br/>
+/// Only one record is in memory at a time. Only versioned patches can be read (version 0 patches return [`SDDEError::UnsupportedVersion`]),
+/// after an error the reader yields no more items.
+pub struct PatchReader<R: Read> {
+    source: StreamSource<R>,
+    header: PatchHeader,
+    /// Control byte and index of the next record, which were read to find the end of the current item
+    next_record: Option<(u8, Index)>,
+    failed: bool,
+}
+
+impl<R: Read> PatchReader<R> {
+    /// Reads the header of the patch
+    pub fn new(reader: R) -> Result<PatchReader<R>, SDDEError> {
+        let mut source = StreamSource::new(reader);
+        if source.read_vec(PATCH_MAGIC.len()).ok().as_deref() != Some(PATCH_MAGIC.as_slice()) {
+            return Err(SDDEError::UnsupportedVersion { version: 0, offset: 0 });
+        }
+        let header = PatchHeader::read_versioned(&mut source)?;
+        Ok(PatchReader {
+            source,
+            header,
+            next_record: None,
+            failed: false,
+        })
+    }
+
+    pub fn header(&self) -> &PatchHeader {
+        &self.header
+    }
+
+    fn read_item(&mut self) -> Result<Option<(Index, EntryDifference)>, SDDEError> {
+        let Some((mut tag, index)) = self.read_record_start()? else {
+            return Ok(None);
+        };
+        let mut entry = EntryDifference::default();
+        loop {
+            SimpleDirectDeltaEncoding::read_record(&mut self.source, tag, &mut entry)?;
+            match self.read_record_start()? {
+                Some((next_tag, next_index)) if next_index == index => tag = next_tag,
+                next_record => {
+                    self.next_record = next_record;
+                    return Ok(Some((index, entry)));
+                },
+            }
+        }
+    }
+
+    fn read_record_start(&mut self) -> Result<Option<(u8, Index)>, SDDEError> {
+        if let Some(record) = self.next_record.take() {
+            return Ok(Some(record));
+        }
+        if self.source.is_empty()? {
+            return Ok(None);
+        }
+        SimpleDirectDeltaEncoding::read_record_start(&mut self.source).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for PatchReader<R> {
+    type Item = Result<(Index, EntryDifference), SDDEError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let item = self.read_item();
+        self.failed = item.is_err();
+        item.transpose()
+    }
+}
+
+/// Buffered stream with the offset in the patch
+struct StreamSource<R: Read> {
+    reader: BufReader<R>,
+    offset: usize,
+}
+
+impl<R: Read> StreamSource<R> {
+    fn new(reader: R) -> StreamSource<R> {
+        StreamSource {
+            reader: BufReader::new(reader),
+            offset: 0,
+        }
+    }
+
+    fn is_empty(&mut self) -> Result<bool, SDDEError> {
+        let buffer = self.reader.fill_buf().map_err(|err| SDDEError::Io(err.to_string()))?;
+        Ok(buffer.is_empty())
+    }
+}
+
+impl<R: Read> PatchSource for StreamSource<R> {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn read_u8(&mut self) -> Result<u8, SDDEError> {
+        let mut byte = [0];
+        match self.reader.read_exact(&mut byte) {
+            Ok(()) => {
+                self.offset += 1;
+                Ok(byte[0])
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Err(SDDEError::Truncated { offset: self.offset }),
+            Err(err) => Err(SDDEError::Io(err.to_string())),
+        }
+    }
+
+    fn read_vec(&mut self, length: usize) -> Result<Vec<u8>, SDDEError> {
+        // the length is not trusted, so the bytes are read instead of allocated up front
+        let mut bytes = Vec::new();
+        let limit = u64::try_from(length).map_err(|_| SDDEError::LengthOverflow)?;
+        let read = (&mut self.reader)
+            .take(limit)
+            .read_to_end(&mut bytes)
+            .map_err(|err| SDDEError::Io(err.to_string()))?;
+        self.offset += read;
+        if read < length {
+            return Err(SDDEError::Truncated { offset: self.offset });
+        }
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod stream {
+    use std::collections::BTreeMap;
+
+    use crate::tests::props;
+    use crate::*;
+
+    fn new_data() -> Vec<IndexedData> {
+        vec![
+            IndexedData::new(0, "Hello brave World".as_bytes().to_vec()),
+            IndexedData::new(2, "abcdefghijklm-nopqrstuvwxyz".repeat(100).into_bytes()),
+            IndexedData::new(3, "Test 1".as_bytes().to_vec()),
+        ]
+    }
+
+    fn sender() -> SimpleDirectDeltaEncoding {
+        let mut sender = SimpleDirectDeltaEncoding::new(&props());
+        sender.set_diff_algorithm(BlockDiff::new(8));
+        sender.set_reversible(true);
+        sender.change_index_mapping(0, "greeting".as_bytes());
+        sender.change_index_mapping(3, "copy".as_bytes());
+        sender
+    }
+
+    /// Writer which fails after the limit of bytes
+    struct LimitedWriter {
+        limit: usize,
+        bytes: Vec<u8>,
+    }
+
+    impl std::io::Write for LimitedWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.bytes.len() + buf.len() > self.limit {
+                return Err(std::io::Error::other("disk full"));
+            }
+            self.bytes.extend(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_patch_to_stream() {
+        let patch = sender().patch(&new_data());
+        let mut sender = sender();
+        let streamed = sender.patch_to_writer(&new_data(), Vec::new()).unwrap();
+
+        // the same bytes with the target crc in the header
+        assert_eq!(streamed, patch);
+        let (header, _) = PatchHeader::from_bytes(&streamed).unwrap();
+        assert_eq!(sender.crc, header.target_crc);
+        assert_eq!(sender.crc, ChecksumAlgorithm::default().checksum(&sender.get_state()));
+
+        let mut receiver = SimpleDirectDeltaEncoding::new(&props());
+        assert!(receiver.apply_patch(&streamed).is_ok());
+        assert_eq!(receiver.get_state(), sender.get_state());
+    }
+
+    #[test]
+    fn write_patch_with_removed_index_mapping() {
+        let mut sender = sender();
+        sender.patch(&new_data());
+        sender.remove_index_mapping(0);
+        sender.change_index_mapping(3, "moved".as_bytes());
+        let mut streaming_sender = sender.clone();
+        let patch = sender.patch(&props());
+        assert_eq!(streaming_sender.patch_to_writer(&props(), Vec::new()).unwrap(), patch);
+        assert_eq!(streaming_sender.get_state(), sender.get_state());
+    }
+
+    #[test]
+    fn write_error_keeps_data() {
+        let mut sender = sender();
+        let (crc, state) = (sender.crc.clone(), sender.get_state());
+        let writer = LimitedWriter { limit: 40, bytes: Vec::new() };
+        assert!(matches!(sender.patch_to_writer(&new_data(), writer), Err(SDDEError::Io(_))));
+        assert_eq!(sender.crc, crc);
+        assert_eq!(sender.get_state(), state);
+        // the index mappings are still part of the next patch
+        assert_eq!(sender.patch(&new_data()), self::sender().patch(&new_data()));
+    }
+
+    #[test]
+    fn read_patch_from_stream() {
+        let patch = sender().patch(&new_data());
+        let expected = SimpleDirectDeltaEncoding::get_differences(&patch).unwrap();
+        let mut reader = PatchReader::new(patch.as_slice()).unwrap();
+        assert_eq!(reader.header(), &PatchHeader::from_bytes(&patch).unwrap().0);
+
+        let mut items: BTreeMap<Index, Vec<EntryDifference>> = BTreeMap::new();
+        for item in reader.by_ref() {
+            let (index, entry) = item.unwrap();
+            items.entry(index).or_default().push(entry);
+        }
+        assert_eq!(items.keys().collect::<Vec<_>>(), expected.keys().collect::<Vec<_>>());
+        for (index, entries) in items {
+            let expected = &expected[&index];
+            let data = entries.iter().find(|x| x.data_changed || x.remove_entry);
+            let map_name = entries.iter().find(|x| x.map_name_changed.is_some() || x.map_name_removed);
+            if let Some(data) = data {
+                assert_eq!(format!("{:?}", data.diffs), format!("{:?}", expected.diffs));
+                assert_eq!((data.remove_entry, data.copy_from), (expected.remove_entry, expected.copy_from));
+                assert_eq!(data.old_data, expected.old_data);
+            }
+            if let Some(map_name) = map_name {
+                assert_eq!(format!("{:?}", map_name.map_name_changed), format!("{:?}", expected.map_name_changed));
+                assert_eq!(map_name.old_map_name, expected.old_map_name);
+            }
+            assert_eq!(data.is_some(), expected.data_changed || expected.remove_entry);
+        }
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn read_invalid_stream() {
+        let patch = sender().patch(&new_data());
+        let mut reader = PatchReader::new(&patch[..patch.len() - 1]).unwrap();
+        let results: Vec<_> = reader.by_ref().collect();
+        assert!(matches!(results.last(), Some(Err(SDDEError::Truncated { .. }))));
+        assert!(reader.next().is_none());
+
+        let mut changed = patch.clone();
+        let (_, header_length) = PatchHeader::from_bytes(&patch).unwrap();
+        changed[header_length] = b'x';
+        let result = PatchReader::new(changed.as_slice()).unwrap().next();
+        assert!(matches!(result, Some(Err(SDDEError::UnknownControlByte { byte: b'x', .. }))));
+
+        assert!(PatchReader::new(&patch[..3]).is_err());
+        assert!(PatchReader::new(&patch[..8]).is_err());
+    }
+}
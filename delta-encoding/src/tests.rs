@@ -1,8 +1,18 @@
+/// Data with three indexes for the tests of the patch operations
+#[cfg(test)]
+pub(crate) fn props() -> Vec<crate::IndexedData> {
+    vec![
+        crate::IndexedData::new(0, "Hello World".as_bytes().to_vec()),
+        crate::IndexedData::new(1, "Test 1".as_bytes().to_vec()),
+        crate::IndexedData::new(2, "abcdefghijklmnopqrstuvwxyz".as_bytes().to_vec()),
+    ]
+}
+
 #[cfg(test)]
 mod patch_data {
     use std::collections::BTreeMap;
 
-    use crate::{IndexedData, SimpleDirectDeltaEncoding};
+    use crate::{BlockDiff, ChecksumAlgorithm, Index, IndexedData, LcsDiff, PositionalDiff, SDDEError, SimpleDirectDeltaEncoding, RECORD_COPY_ENTRY};
 
     #[test]
     fn patch_data() {
@@ -12,7 +22,10 @@ mod patch_data {
         let diff_data = sdd.patch(new_data);
         assert_eq!(
             diff_data,
-            vec![10, 49, 51, 54, 55, 54, 57, 54, 57, 55, 49, 118, 0, 6, 105, 58, 4, 45, 1, 50]
+            vec![
+                83, 68, 68, 69, 1, 0, 0, 10, 51, 48, 57, 50, 49, 49, 53, 48, 51, 57, 10, 49, 51, 57, 50, 50, 52, 52, 52,
+                54, 49, 101, 0, 1, 105, 4, 1, 50
+            ]
         );
     }
 
@@ -207,7 +220,255 @@ mod patch_data {
         );
     }
 
-    fn fold_data_collection(data_collection: &BTreeMap<u8, IndexedData>) -> Vec<u8> {
+    #[test]
+    fn patch_remove_index_mapping() {
+        let props = vec![IndexedData::new(0, "Test".as_bytes().to_vec()), IndexedData::new(1, "Test2".as_bytes().to_vec())];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        sdd.change_index_mapping(0, "t1".as_bytes());
+        sdd.change_index_mapping(1, "t2".as_bytes());
+        sdd.apply_index_mappings();
+        sdd.remove_index_mapping(0);
+        let diff_data = sdd.patch(&props);
+        assert!(!sdd.get_index_mapping().contains_key(&0));
+
+        let mut sdd2 = SimpleDirectDeltaEncoding::new(&props);
+        sdd2.change_index_mapping(0, "t1".as_bytes());
+        sdd2.change_index_mapping(1, "t2".as_bytes());
+        sdd2.apply_index_mappings();
+        let result = sdd2.apply_patch(&diff_data).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].index, 0);
+        assert!(result[0].map_name_removed);
+        assert!(!result[0].entry_removed);
+        assert_eq!(result[0].data, "Test".as_bytes());
+        assert!(!sdd2.get_index_mapping().contains_key(&0));
+        assert_eq!(sdd2.get_index_mapping().get(&1).unwrap().current, "t2".as_bytes());
+        assert_eq!(sdd2.data_collection.len(), 2);
+    }
+
+    #[test]
+    fn patch_remove_property_and_index_mapping() {
+        let props = vec![IndexedData::new(0, "Test".as_bytes().to_vec()), IndexedData::new(1, "Test2".as_bytes().to_vec())];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        sdd.change_index_mapping(1, "t2".as_bytes());
+        sdd.apply_index_mappings();
+        sdd.remove_index_mapping(1);
+        let diff_data = sdd.patch(&props[..1]);
+
+        let mut sdd2 = SimpleDirectDeltaEncoding::new(&props);
+        sdd2.change_index_mapping(1, "t2".as_bytes());
+        sdd2.apply_index_mappings();
+        let result = sdd2.apply_patch(&diff_data).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].index, 1);
+        assert!(result[0].data.is_empty());
+        assert!(result[0].entry_removed);
+        assert!(result[0].map_name_removed);
+        assert!(sdd2.get_index_mapping().is_empty());
+        assert!(!sdd2.data_collection.contains_key(&1));
+    }
+
+    #[test]
+    fn state_is_index_aware() {
+        let sdd = SimpleDirectDeltaEncoding::new(&[IndexedData::new(0, "ab".as_bytes().to_vec()), IndexedData::new(1, "c".as_bytes().to_vec())]);
+        let sdd2 = SimpleDirectDeltaEncoding::new(&[IndexedData::new(0, "a".as_bytes().to_vec()), IndexedData::new(1, "bc".as_bytes().to_vec())]);
+        let sdd3 = SimpleDirectDeltaEncoding::new(&[IndexedData::new(0, "ab".as_bytes().to_vec()), IndexedData::new(2, "c".as_bytes().to_vec())]);
+        assert_eq!(sdd.get_state(), vec![2, 0, 2, b'a', b'b', 1, 1, b'c', 0]);
+        assert_ne!(sdd.get_state(), sdd2.get_state());
+        assert_ne!(sdd.crc, sdd2.crc);
+        assert_ne!(sdd.crc, sdd3.crc);
+
+        // a patch for one state can not be applied to the other
+        let mut sender = sdd.clone();
+        let diff_data = sender.patch(&[IndexedData::new(0, "abc".as_bytes().to_vec())]);
+        let mut receiver = sdd2.clone();
+        assert!(matches!(receiver.apply_patch(&diff_data), Err(SDDEError::CRC(_))));
+    }
+
+    #[test]
+    fn state_contains_index_mappings() {
+        let props = vec![IndexedData::new(0, "Test".as_bytes().to_vec())];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        let crc = sdd.crc.clone();
+        sdd.change_index_mapping(0, "t1".as_bytes());
+        sdd.apply_index_mappings();
+        assert_eq!(sdd.get_state(), vec![1, 0, 4, b'T', b'e', b's', b't', 1, 0, 2, b't', b'1']);
+        assert_ne!(sdd.crc, crc);
+
+        // the receiver without the index mapping has a different state
+        let diff_data = sdd.patch(&[IndexedData::new(0, "Test2".as_bytes().to_vec())]);
+        let mut sdd2 = SimpleDirectDeltaEncoding::new(&props);
+        assert!(matches!(sdd2.apply_patch(&diff_data), Err(SDDEError::CRC(_))));
+    }
+
+    #[test]
+    fn patch_lcs_diff_algorithm() {
+        let props = vec![IndexedData::new(0, "World".as_bytes().to_vec())];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        sdd.set_diff_algorithm(LcsDiff::default());
+        sdd.change_index_mapping(0, "name".as_bytes());
+        let new_data = &[IndexedData::new(0, "Hello World".as_bytes().to_vec())];
+        let diff_data = sdd.patch(new_data);
+
+        let mut sdd_positional = SimpleDirectDeltaEncoding::new(&props);
+        sdd_positional.change_index_mapping(0, "name".as_bytes());
+        assert!(diff_data.len() < sdd_positional.patch(new_data).len());
+
+        let mut sdd2 = SimpleDirectDeltaEncoding::new(&props);
+        let result_data = sdd2.apply_patch(&diff_data);
+        assert!(result_data.is_ok());
+        assert_eq!(
+            fold_data_collection(&sdd.data_collection),
+            fold_data_collection(&sdd2.data_collection),
+        );
+        assert_eq!(sdd2.get_index_mapping().get(&0).unwrap().current, "name".as_bytes());
+    }
+
+    #[test]
+    fn patch_moved_property() {
+        let value_1 = "The first value which is moved".as_bytes().to_vec();
+        let value_2 = "The second value which is moved".as_bytes().to_vec();
+        let props = vec![IndexedData::new(0, value_1.clone()), IndexedData::new(1, value_2.clone())];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        let new_data = &[IndexedData::new(0, value_2.clone()), IndexedData::new(1, value_1.clone())];
+        let diff_data = sdd.patch(new_data);
+        let differences = SimpleDirectDeltaEncoding::get_differences(&diff_data).unwrap();
+        assert_eq!(differences.get(&0).unwrap().copy_from, Some(1));
+        assert_eq!(differences.get(&1).unwrap().copy_from, Some(0));
+        assert!(differences.values().all(|x| x.diffs.is_empty()));
+
+        let mut sdd2 = SimpleDirectDeltaEncoding::new(&props);
+        assert!(sdd2.apply_patch(&diff_data).is_ok());
+        assert_eq!(sdd2.data_collection.get(&0).unwrap().data, value_2);
+        assert_eq!(sdd2.data_collection.get(&1).unwrap().data, value_1);
+    }
+
+    #[test]
+    fn patch_copy_to_new_property() {
+        let value = "A value which is moved to a new index".as_bytes().to_vec();
+        let props = vec![IndexedData::new(0, value.clone())];
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        let diff_data = sdd.patch(&[IndexedData::new(5, value.clone())]);
+        let records = SimpleDirectDeltaEncoding::get_differences_bytes_with_crc(&diff_data).unwrap();
+        assert_eq!(records, vec![RECORD_COPY_ENTRY, 5, 0, 0, b'r', 0]);
+
+        let mut sdd2 = SimpleDirectDeltaEncoding::new(&props);
+        assert!(sdd2.apply_patch(&diff_data).is_ok());
+        assert!(!sdd2.data_collection.contains_key(&0));
+        assert_eq!(sdd2.data_collection.get(&5).unwrap().data, value);
+    }
+
+    #[test]
+    fn patch_index_beyond_u8() {
+        let props: Vec<IndexedData> = (0..1_000).map(|i| IndexedData::new(i, i.to_string().into_bytes())).collect();
+        let mut sdd = SimpleDirectDeltaEncoding::new(&props);
+        sdd.change_index_mapping(70_000, "large".as_bytes());
+        let mut new_data = props.clone();
+        new_data[300].data = "changed".as_bytes().to_vec();
+        new_data.push(IndexedData::new(70_000, "Test".as_bytes().to_vec()));
+        let diff_data = sdd.patch(&new_data);
+
+        let mut sdd2 = SimpleDirectDeltaEncoding::new(&props);
+        let result = sdd2.apply_patch(&diff_data).unwrap();
+        assert_eq!(result.iter().map(|x| x.index).collect::<Vec<Index>>(), vec![300, 70_000]);
+        assert_eq!(sdd2.data_collection.len(), 1_001);
+        assert_eq!(sdd2.data_collection.get(&300).unwrap().data, "changed".as_bytes());
+        assert_eq!(sdd2.data_collection.get(&70_000).unwrap().data, "Test".as_bytes());
+        assert_eq!(sdd2.get_index_mapping().get(&70_000).unwrap().current, "large".as_bytes());
+    }
+
+    /// Reproducible random numbers below the max value
+    fn random_numbers(seed: u32) -> impl FnMut(u32) -> u32 {
+        let mut state = seed;
+        move |max| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state % max
+        }
+    }
+
+    fn chain_patches(checksum_algorithm: ChecksumAlgorithm, seed: u32) {
+        let mut random = random_numbers(seed);
+        let props: Vec<IndexedData> = (0..5).map(|i| IndexedData::new(i, format!("value {}", i).into_bytes())).collect();
+        let mut current: BTreeMap<Index, Vec<u8>> = props.iter().map(|x| (x.index, x.data.clone())).collect();
+        let mut sender = SimpleDirectDeltaEncoding::new(&props);
+        let mut receiver = SimpleDirectDeltaEncoding::new(&props);
+        sender.set_checksum_algorithm(checksum_algorithm);
+        receiver.set_checksum_algorithm(checksum_algorithm);
+
+        for round in 0..50 {
+            match round % 3 {
+                0 => sender.set_diff_algorithm(PositionalDiff::default()),
+                1 => sender.set_diff_algorithm(LcsDiff::default()),
+                _ => sender.set_diff_algorithm(BlockDiff::new(4)),
+            }
+            let index = random(10);
+            match random(4) {
+                0 => {
+                    current.remove(&index);
+                },
+                1 => {
+                    current.insert(index, format!("round {} {}", round, "x".repeat(random(20) as usize)).into_bytes());
+                },
+                _ => {
+                    let value = current.entry(index).or_default();
+                    let position = random(value.len() as u32 + 1) as usize;
+                    value.splice(position..position, format!("<{}>", round).into_bytes());
+                },
+            }
+            if round % 5 == 0 {
+                sender.change_index_mapping(random(10), format!("key {}", round).as_bytes());
+            }
+            if round % 7 == 0 {
+                sender.remove_index_mapping(random(10));
+            }
+
+            let new_data: Vec<IndexedData> = current.iter().map(|(index, data)| IndexedData::new(*index, data.clone())).collect();
+            let diff_data = sender.patch(&new_data);
+            assert_eq!(sender.crc, checksum_algorithm.checksum(&sender.get_state()));
+            let source_crc = receiver.crc.clone();
+            let result = receiver.apply_patch(&diff_data);
+            assert!(result.is_ok(), "round {} {:?}", round, result);
+            assert_eq!(receiver.get_state(), sender.get_state(), "round {}", round);
+            assert_eq!(receiver.crc, sender.crc, "round {}", round);
+
+            // the same patch can not be applied twice (if it changed the state)
+            if source_crc != receiver.crc {
+                assert!(receiver.apply_patch(&diff_data).is_err(), "round {}", round);
+            }
+        }
+    }
+
+    #[test]
+    fn chain_sequential_patches() {
+        chain_patches(ChecksumAlgorithm::DispnetCrc, 0x2545_f491);
+        chain_patches(ChecksumAlgorithm::DispnetCrc, 0x9e37_79b9);
+    }
+
+    #[test]
+    fn chain_sequential_patches_with_checksum_algorithms() {
+        chain_patches(ChecksumAlgorithm::Crc32c, 0x1234_5678);
+        chain_patches(ChecksumAlgorithm::Blake3, 0x8765_4321);
+    }
+
+    #[test]
+    fn chain_patches_of_receiver() {
+        // the receiver of a patch can create the next patch for the sender
+        let props = vec![IndexedData::new(0, "Test".as_bytes().to_vec())];
+        let mut first = SimpleDirectDeltaEncoding::new(&props);
+        let mut second = SimpleDirectDeltaEncoding::new(&props);
+        for round in 0..30 {
+            let (sender, receiver) = if round % 2 == 0 { (&mut first, &mut second) } else { (&mut second, &mut first) };
+            let value = format!("Test {}", round).into_bytes();
+            let diff_data = sender.patch(&[IndexedData::new(0, value.clone())]);
+            assert!(receiver.apply_patch(&diff_data).is_ok(), "round {}", round);
+            assert_eq!(receiver.data_collection.get(&0).unwrap().data, value);
+            assert_eq!(receiver.crc, sender.crc);
+        }
+    }
+
+    fn fold_data_collection(data_collection: &BTreeMap<Index, IndexedData>) -> Vec<u8> {
         SimpleDirectDeltaEncoding::fold_index(&data_collection.values().cloned().collect::<Vec<_>>())
     }
 
@@ -224,14 +485,14 @@ mod patch_data {
 
         for (index, (key, value)) in json_source.as_object().unwrap().iter().enumerate() {
             src_data.push((key, IndexedData::new(
-                index as u8,
+                index as Index,
                 value.to_string().as_bytes().to_vec(),
             )));
         }
 
         for (index, (key, value)) in json_changes.as_object().unwrap().iter().enumerate() {
             changes_data.push((key, IndexedData::new(
-                index as u8,
+                index as Index,
                 value.to_string().as_bytes().to_vec(),
             )));
         }
@@ -0,0 +1,323 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::diff_algorithm::DiffAlgorithm;
+use crate::keyed::KeyedDeltaEncoding;
+use crate::SDDEError;
+
+/// Change of a file between two states of a directory tree (the paths are relative with `/` separators)
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeChange {
+    Added(String),
+    Removed(String),
+    Modified(String),
+    /// The file is moved to another path, the content can be changed too
+    Renamed { from: String, to: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct DirectoryPatch {
+    pub patch: Vec<u8>,
+    pub changes: Vec<TreeChange>,
+}
+
+/// Delta encoding of the files of a directory tree, every relative file path is a key of a [`KeyedDeltaEncoding`]
+///
+///
+/// One patch contains the modified, added, removed and renamed files (a rename is detected by the content of the file).<br/>
+/// Only regular files are part of the tree, empty directories, symbolic links and file permissions are not.
+#[derive(Clone)]
+pub struct DirectoryDelta {
+    encoding: KeyedDeltaEncoding,
+}
+
+impl DirectoryDelta {
+    /// Reads the files of the directory as the base state
+    pub fn new(dir: &Path) -> Result<DirectoryDelta, SDDEError> {
+        Ok(DirectoryDelta {
+            encoding: KeyedDeltaEncoding::new(read_tree(dir)?),
+        })
+    }
+
+    /// Files of the current state with the relative paths
+    pub fn files(&self) -> BTreeMap<String, Vec<u8>> {
+        to_files(self.encoding.get_map())
+    }
+
+    /// Change the algorithm used to create the differences of the files in the next patches
+    pub fn set_diff_algorithm(&mut self, diff_algorithm: impl DiffAlgorithm + 'static) {
+        self.encoding.set_diff_algorithm(diff_algorithm);
+    }
+
+    /// Patch from the current state to the files of the new directory
+    pub fn patch(&mut self, new_dir: &Path) -> Result<DirectoryPatch, SDDEError> {
+        let before = self.encoding.clone();
+        let patch = self.encoding.patch(read_tree(new_dir)?);
+        Ok(DirectoryPatch {
+            patch,
+            changes: tree_changes(&before, &self.encoding),
+        })
+    }
+
+    /// Apply the patch to the current state (the files on the disk are not changed)
+    pub fn apply_patch(&mut self, patch: &[u8]) -> Result<Vec<TreeChange>, SDDEError> {
+        let before = self.encoding.clone();
+        let files = self.encoding.apply_patch(patch)?;
+        // the paths of the patch are written to the disk, so they have to stay inside of the directory
+        let invalid = files.keys().find_map(|key| match std::str::from_utf8(key) {
+            Ok(path) => relative_path(path).err(),
+            Err(_) => Some(SDDEError::InvalidPath(format!("{} is not UTF-8", String::from_utf8_lossy(key)))),
+        });
+        if let Some(err) = invalid {
+            self.encoding = before;
+            return Err(err);
+        }
+        Ok(tree_changes(&before, &self.encoding))
+    }
+}
+
+/// Applies the patch to the files of the base directory and writes the result to the target directory
+///
+///
+/// The target is the base directory (patched in place) or a directory which does not exist yet.<br/>
+/// Empty directories, symbolic links and the permissions of the files are kept.<br/>
+/// The base is copied to a staging directory next to the target and patched there, so on an error the target stays untouched.
+/// The patched staging directory is renamed to the target, in place the base is renamed to a backup directory first
+/// and restored if the staging directory can not be renamed.
+pub fn apply_directory_patch(base: &Path, patch: &[u8], target: &Path) -> Result<Vec<TreeChange>, SDDEError> {
+    let base_files = read_tree(base)?;
+    let mut delta = DirectoryDelta {
+        encoding: KeyedDeltaEncoding::new(base_files.clone()),
+    };
+    let changes = delta.apply_patch(patch)?;
+    let in_place = target.exists();
+    if in_place && !same_path(base, target)? {
+        return Err(io_error(target, "the target exists and is not the base directory"));
+    }
+
+    let staging = sibling_path(target, "staging")?;
+    remove_dir(&staging)?;
+    let write = || -> Result<(), SDDEError> {
+        copy_tree(base, &staging)?;
+        write_changes(base, &staging, &base_files, &delta.files(), &changes)
+    };
+    if let Err(err) = write() {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(err);
+    }
+
+    if in_place {
+        let backup = sibling_path(target, "backup")?;
+        remove_dir(&backup)?;
+        if let Err(err) = std::fs::rename(target, &backup) {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(io_error(target, err));
+        }
+        if let Err(err) = std::fs::rename(&staging, target) {
+            let _ = std::fs::rename(&backup, target);
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(io_error(target, err));
+        }
+        remove_dir(&backup)?;
+    } else if let Err(err) = std::fs::rename(&staging, target) {
+        let _ = std::fs::remove_dir_all(&staging);
+        return Err(io_error(target, err));
+    }
+    Ok(changes)
+}
+
+/// Removes the files which are not part of the new files from the copy of the base and writes the changed files
+///
+///
+/// The files are removed first, so a file can replace a directory with the same path and the other way around.<br/>
+/// A changed file keeps the permissions of the old file (or the file it is renamed from) in the base.
+fn write_changes(
+    base: &Path,
+    dir: &Path,
+    base_files: &BTreeMap<String, Vec<u8>>,
+    files: &BTreeMap<String, Vec<u8>>,
+    changes: &[TreeChange],
+) -> Result<(), SDDEError> {
+    for path in base_files.keys().filter(|path| !files.contains_key(*path)) {
+        let file = dir.join(relative_path(path)?);
+        std::fs::remove_file(&file).map_err(|err| io_error(&file, err))?;
+        // the directories which are empty without the file are removed (remove_dir fails for other directories)
+        for parent in file.ancestors().skip(1).take_while(|x| *x != dir) {
+            if std::fs::remove_dir(parent).is_err() {
+                break;
+            }
+        }
+    }
+
+    for (path, data) in files.iter().filter(|(path, data)| base_files.get(*path) != Some(data)) {
+        let file = dir.join(relative_path(path)?);
+        check_file_path(dir, &file)?;
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| io_error(parent, err))?;
+        }
+        std::fs::write(&file, data).map_err(|err| io_error(&file, err))?;
+
+        let old_path = changes
+            .iter()
+            .find_map(|change| match change {
+                TreeChange::Renamed { from, to } if to == path => Some(from),
+                _ => None,
+            })
+            .unwrap_or(path);
+        if base_files.contains_key(old_path) {
+            let old_file = base.join(relative_path(old_path)?);
+            let permissions = std::fs::metadata(&old_file).map_err(|err| io_error(&old_file, err))?.permissions();
+            std::fs::set_permissions(&file, permissions).map_err(|err| io_error(&file, err))?;
+        }
+    }
+    Ok(())
+}
+
+/// The parents of a written file have to be directories (a symbolic link could point outside of the directory)
+/// and an existing file has to be a regular file
+fn check_file_path(dir: &Path, file: &Path) -> Result<(), SDDEError> {
+    for (i, path) in file.ancestors().take_while(|x| *x != dir).enumerate() {
+        let Ok(metadata) = std::fs::symlink_metadata(path) else {
+            continue;
+        };
+        let valid = if i == 0 { metadata.is_file() } else { metadata.is_dir() };
+        if !valid {
+            return Err(SDDEError::InvalidPath(format!(
+                "{} is a {} in the target",
+                path.display(),
+                if metadata.is_symlink() { "symbolic link" } else { "different file type" }
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Copies the directory tree with the empty directories, symbolic links and permissions
+fn copy_tree(from: &Path, to: &Path) -> Result<(), SDDEError> {
+    let mut dirs: Vec<(PathBuf, PathBuf)> = vec![(from.to_path_buf(), to.to_path_buf())];
+    while let Some((from, to)) = dirs.pop() {
+        std::fs::create_dir(&to).map_err(|err| io_error(&to, err))?;
+        let permissions = std::fs::metadata(&from).map_err(|err| io_error(&from, err))?.permissions();
+        std::fs::set_permissions(&to, permissions).map_err(|err| io_error(&to, err))?;
+        for entry in std::fs::read_dir(&from).map_err(|err| io_error(&from, err))? {
+            let entry = entry.map_err(|err| io_error(&from, err))?;
+            let (path, copy) = (entry.path(), to.join(entry.file_name()));
+            let file_type = entry.file_type().map_err(|err| io_error(&path, err))?;
+            if file_type.is_dir() {
+                dirs.push((path, copy));
+            } else if file_type.is_file() {
+                // the permissions are copied too
+                std::fs::copy(&path, &copy).map_err(|err| io_error(&path, err))?;
+            } else if file_type.is_symlink() {
+                copy_symlink(&path, &copy)?;
+            } else {
+                return Err(io_error(&path, "only files, directories and symbolic links can be copied"));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> Result<(), SDDEError> {
+    let link = std::fs::read_link(from).map_err(|err| io_error(from, err))?;
+    std::os::unix::fs::symlink(link, to).map_err(|err| io_error(to, err))
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, _to: &Path) -> Result<(), SDDEError> {
+    Err(io_error(from, "symbolic links are only copied on unix, patch the base directory in place"))
+}
+
+/// Files of the directory tree with the relative paths as keys
+pub fn read_tree(dir: &Path) -> Result<BTreeMap<String, Vec<u8>>, SDDEError> {
+    let mut files: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    let mut dirs: Vec<(PathBuf, String)> = vec![(dir.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir).map_err(|err| io_error(&dir, err))? {
+            let entry = entry.map_err(|err| io_error(&dir, err))?;
+            let path = entry.path();
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|_| SDDEError::InvalidPath(format!("{} is not UTF-8", path.display())))?;
+            let key = format!("{}{}", prefix, name);
+            let file_type = entry.file_type().map_err(|err| io_error(&path, err))?;
+            if file_type.is_dir() {
+                dirs.push((path, format!("{}/", key)));
+            } else if file_type.is_file() {
+                files.insert(key, std::fs::read(&path).map_err(|err| io_error(&path, err))?);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Changes of the files by the index of the keys (a renamed file keeps its index)
+fn tree_changes(before: &KeyedDeltaEncoding, after: &KeyedDeltaEncoding) -> Vec<TreeChange> {
+    let by_index = |encoding: &KeyedDeltaEncoding| -> BTreeMap<_, _> {
+        to_files(encoding.get_map())
+            .into_iter()
+            .filter_map(|(path, data)| encoding.get_index(path.as_bytes()).map(|index| (index, (path, data))))
+            .collect()
+    };
+    let (old, new) = (by_index(before), by_index(after));
+    let mut changes: Vec<TreeChange> = Vec::new();
+    for (index, (path, data)) in old.iter() {
+        match new.get(index) {
+            None => changes.push(TreeChange::Removed(path.clone())),
+            Some((new_path, _)) if new_path != path => changes.push(TreeChange::Renamed {
+                from: path.clone(),
+                to: new_path.clone(),
+            }),
+            Some((_, new_data)) if new_data != data => changes.push(TreeChange::Modified(path.clone())),
+            Some(_) => {},
+        }
+    }
+    for (index, (path, _)) in new.iter() {
+        if !old.contains_key(index) {
+            changes.push(TreeChange::Added(path.clone()));
+        }
+    }
+    changes
+}
+
+fn to_files(map: BTreeMap<Vec<u8>, Vec<u8>>) -> BTreeMap<String, Vec<u8>> {
+    map.into_iter()
+        .map(|(key, value)| (String::from_utf8_lossy(&key).into_owned(), value))
+        .collect()
+}
+
+/// Relative path of a key, a key with an absolute path or a parent component would be outside of the directory
+fn relative_path(key: &str) -> Result<PathBuf, SDDEError> {
+    let path = PathBuf::from(key);
+    let valid = !key.is_empty() && path.components().all(|component| matches!(component, Component::Normal(_)));
+    if !valid {
+        return Err(SDDEError::InvalidPath(format!("{} is not a relative path inside of the directory", key)));
+    }
+    Ok(path)
+}
+
+/// Path next to the target for the staging or backup directory
+fn sibling_path(target: &Path, suffix: &str) -> Result<PathBuf, SDDEError> {
+    let name = target
+        .file_name()
+        .ok_or_else(|| io_error(target, "the target has no directory name"))?;
+    Ok(target.with_file_name(format!(".{}.sdde-{}", name.to_string_lossy(), suffix)))
+}
+
+fn same_path(a: &Path, b: &Path) -> Result<bool, SDDEError> {
+    let canonical = |path: &Path| path.canonicalize().map_err(|err| io_error(path, err));
+    Ok(canonical(a)? == canonical(b)?)
+}
+
+fn remove_dir(dir: &Path) -> Result<(), SDDEError> {
+    if dir.exists() {
+        std::fs::remove_dir_all(dir).map_err(|err| io_error(dir, err))?;
+    }
+    Ok(())
+}
+
+fn io_error(path: &Path, error: impl std::fmt::Display) -> SDDEError {
+    SDDEError::Io(format!("{}: {}", path.display(), error))
+}
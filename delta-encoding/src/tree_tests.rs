@@ -0,0 +1,188 @@
+#[cfg(test)]
+mod tree {
+    use std::collections::BTreeMap;
+    use std::path::{Path, PathBuf};
+
+    use crate::*;
+
+    /// Empty directory for the files of a test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sdde_tree_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_tree(dir: &Path, files: &[(&str, &str)]) {
+        for (path, content) in files {
+            let file = dir.join(path);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, content).unwrap();
+        }
+    }
+
+    fn files(entries: &[(&str, &str)]) -> BTreeMap<String, Vec<u8>> {
+        entries
+            .iter()
+            .map(|(path, content)| (path.to_string(), content.as_bytes().to_vec()))
+            .collect()
+    }
+
+    const CONFIG: &str = "listen = 0.0.0.0:8080\nworkers = 4\nlog_level = info\ntimeout = 30\n";
+
+    fn old_files() -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("app.toml", CONFIG),
+            ("certs/ca.pem", "-----BEGIN CERTIFICATE-----"),
+            ("sites/default.conf", "server default"),
+            ("sites/old.conf", "server old"),
+        ]
+    }
+
+    fn new_files() -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("app.toml", "listen = 0.0.0.0:8080\nworkers = 8\nlog_level = info\ntimeout = 30\n"),
+            ("certs/root-ca.pem", "-----BEGIN CERTIFICATE-----"),
+            ("sites/default.conf", "server default"),
+            ("sites/new/edge.conf", "server edge"),
+        ]
+    }
+
+    #[test]
+    fn patch_directory_tree() {
+        let dir = test_dir("patch");
+        let (old, new) = (dir.join("old"), dir.join("new"));
+        write_tree(&old, &old_files());
+        write_tree(&new, &new_files());
+
+        let mut sender = DirectoryDelta::new(&old).unwrap();
+        assert_eq!(sender.files(), files(&old_files()));
+        let patch = sender.patch(&new).unwrap();
+        let expected = vec![
+            TreeChange::Modified("app.toml".to_owned()),
+            TreeChange::Renamed {
+                from: "certs/ca.pem".to_owned(),
+                to: "certs/root-ca.pem".to_owned(),
+            },
+            TreeChange::Removed("sites/old.conf".to_owned()),
+            TreeChange::Added("sites/new/edge.conf".to_owned()),
+        ];
+        assert_eq!(patch.changes, expected);
+
+        let mut receiver = DirectoryDelta::new(&old).unwrap();
+        assert_eq!(receiver.apply_patch(&patch.patch).unwrap(), expected);
+        assert_eq!(receiver.files(), files(&new_files()));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn apply_to_target_directory() {
+        let dir = test_dir("apply");
+        let (old, new, target) = (dir.join("old"), dir.join("new"), dir.join("target"));
+        write_tree(&old, &old_files());
+        write_tree(&new, &new_files());
+        let patch = DirectoryDelta::new(&old).unwrap().patch(&new).unwrap().patch;
+
+        // into a new directory
+        assert!(apply_directory_patch(&old, &patch, &target).is_ok());
+        assert_eq!(read_tree(&target).unwrap(), files(&new_files()));
+        assert_eq!(read_tree(&old).unwrap(), files(&old_files()));
+        // an existing directory which is not the base is not replaced
+        assert!(matches!(apply_directory_patch(&old, &patch, &new), Err(SDDEError::Io(_))));
+
+        // in place
+        assert!(apply_directory_patch(&old, &patch, &old).is_ok());
+        assert_eq!(read_tree(&old).unwrap(), files(&new_files()));
+        assert!(!old.join("sites").join("old.conf").exists());
+        // only the patched directories are left
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|x| x.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["new", "old", "target"]);
+
+        // the patch was created for the old files, the patched directory stays untouched
+        assert!(matches!(apply_directory_patch(&old, &patch, &old), Err(SDDEError::CRC(_))));
+        assert_eq!(read_tree(&old).unwrap(), files(&new_files()));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn replace_directory_with_file() {
+        let dir = test_dir("file_type");
+        let (dirs, file) = (vec![("conf/x", "x"), ("a.txt", "a")], vec![("conf", "x"), ("a.txt", "a")]);
+        // a directory becomes a file with the same path and the other way around
+        for (name, old_files, new_files) in [("to_file", &dirs, &file), ("to_dir", &file, &dirs)] {
+            let (old, new, target) = (dir.join(name).join("old"), dir.join(name).join("new"), dir.join(name).join("target"));
+            write_tree(&old, old_files);
+            write_tree(&new, new_files);
+            let patch = DirectoryDelta::new(&old).unwrap().patch(&new).unwrap().patch;
+
+            for target in [&target, &old] {
+                let result = apply_directory_patch(&old, &patch, target);
+                assert!(result.is_ok(), "{} {:?}", name, result);
+                assert_eq!(read_tree(target).unwrap(), files(new_files));
+            }
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reject_paths_outside_of_directory() {
+        let dir = test_dir("outside");
+        write_tree(&dir, &[("a.txt", "a")]);
+        let mut sender = KeyedDeltaEncoding::new(files(&[("a.txt", "a")]));
+        let patch = sender.patch(files(&[("a.txt", "a"), ("../escaped.txt", "b")]));
+
+        let mut receiver = DirectoryDelta::new(&dir).unwrap();
+        assert!(matches!(receiver.apply_patch(&patch), Err(SDDEError::InvalidPath(_))));
+        assert_eq!(receiver.files(), files(&[("a.txt", "a")]));
+        assert!(matches!(apply_directory_patch(&dir, &patch, &dir), Err(SDDEError::InvalidPath(_))));
+        assert!(!dir.parent().unwrap().join("escaped.txt").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keep_symlinks_and_empty_directories() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = test_dir("links");
+        let (old, new, target) = (dir.join("old"), dir.join("new"), dir.join("target"));
+        write_tree(&old, &[("run.sh", "echo old"), ("data/a.txt", "a")]);
+        write_tree(&new, &[("run.sh", "echo new"), ("data/a.txt", "a")]);
+        std::fs::set_permissions(old.join("run.sh"), std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::create_dir(old.join("empty")).unwrap();
+        std::os::unix::fs::symlink("data/a.txt", old.join("link")).unwrap();
+        let patch = DirectoryDelta::new(&old).unwrap().patch(&new).unwrap().patch;
+
+        for target in [&target, &old] {
+            assert!(apply_directory_patch(&old, &patch, target).is_ok());
+            assert_eq!(read_tree(target).unwrap(), files(&[("run.sh", "echo new"), ("data/a.txt", "a")]));
+            assert!(target.join("empty").is_dir());
+            assert_eq!(std::fs::read_link(target.join("link")).unwrap(), Path::new("data/a.txt"));
+            let mode = std::fs::metadata(target.join("run.sh")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reject_files_behind_symlinks() {
+        let dir = test_dir("behind_links");
+        let (base, outside) = (dir.join("base"), dir.join("outside"));
+        write_tree(&base, &[("a.txt", "a")]);
+        std::fs::create_dir(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, base.join("linked")).unwrap();
+        let mut sender = KeyedDeltaEncoding::new(files(&[("a.txt", "a")]));
+        let patch = sender.patch(files(&[("a.txt", "b"), ("linked/b.txt", "b")]));
+
+        assert!(matches!(apply_directory_patch(&base, &patch, &base), Err(SDDEError::InvalidPath(_))));
+        assert!(!outside.join("b.txt").exists());
+        // the changed file is not replaced on an error
+        assert_eq!(read_tree(&base).unwrap(), files(&[("a.txt", "a")]));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
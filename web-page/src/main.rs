@@ -1,6 +1,6 @@
 use std::collections::BTreeMap;
 
-use simple_direct_delta_encoding::{IndexedData, SimpleDirectDeltaEncoding};
+use simple_direct_delta_encoding::{Index, IndexedData, SimpleDirectDeltaEncoding};
 use wasm_bindgen::JsCast;
 use yew::prelude::*;
 use yew_router::prelude::*;
@@ -284,7 +284,7 @@ fn GeneratePatch() -> Html {
                 let mut properties_indexed: Vec<(String, IndexedData)> = Vec::new();
                 if let Ok(serde_json::Value::Object(map)) = a {
                     for (index, (key, value)) in map.iter().enumerate() {
-                        properties_indexed.push((key.to_string(), IndexedData::new(index as u8, value.to_string().trim().as_bytes().to_vec())));
+                        properties_indexed.push((key.to_string(), IndexedData::new(index as Index, value.to_string().trim().as_bytes().to_vec())));
                     }
                 }
                 properties_indexed
@@ -298,7 +298,7 @@ fn GeneratePatch() -> Html {
         let enc_src = mapped_src.iter().map(|(_, indexed_data)| indexed_data.clone()).collect::<Vec<IndexedData>>();
         let mut enc = SimpleDirectDeltaEncoding::new(&enc_src);
 
-        let keys = mapped_src.iter().map(|(key, i)| (key.clone(), i.index)).collect::<Vec<(String, u8)>>();
+        let keys = mapped_src.iter().map(|(key, i)| (key.clone(), i.index)).collect::<Vec<(String, Index)>>();
         if !keys.is_empty() {
             for (key, index) in keys {
                 enc.change_index_mapping(index, key.as_bytes());
@@ -312,7 +312,7 @@ fn GeneratePatch() -> Html {
                 let mut properties_indexed: Vec<(String, IndexedData)> = Vec::new();
                 if let Ok(serde_json::Value::Object(map)) = a {
                     for (index, (key, value)) in map.iter().enumerate() {
-                        properties_indexed.push((key.to_string(), IndexedData::new(index as u8, value.to_string().trim().as_bytes().to_vec())));
+                        properties_indexed.push((key.to_string(), IndexedData::new(index as Index, value.to_string().trim().as_bytes().to_vec())));
                     }
                 }
                 properties_indexed
@@ -324,7 +324,7 @@ fn GeneratePatch() -> Html {
         };
 
         // apply the target key mappings and then patch the the target source data
-        let keys = mapped_target.iter().map(|(key, i)| (key.clone(), i.index)).collect::<Vec<(String, u8)>>();
+        let keys = mapped_target.iter().map(|(key, i)| (key.clone(), i.index)).collect::<Vec<(String, Index)>>();
         if !keys.is_empty() {
             for (key, index) in keys {
                 enc.change_index_mapping(index, key.as_bytes());
@@ -339,11 +339,14 @@ fn GeneratePatch() -> Html {
         if enc_data != *encoding_data_bytes {
             encoding_data_bytes.set(enc_data);
 
-            let diffs = SimpleDirectDeltaEncoding::get_differences(&patch);
+            let diffs = SimpleDirectDeltaEncoding::get_differences(&patch).unwrap_or_default();
             current_diffs.set(diffs);
             current_patch.set(patch.clone());
-            current_byte_size
-                .set(SimpleDirectDeltaEncoding::get_differences_bytes_with_crc(&patch).len());
+            current_byte_size.set(
+                SimpleDirectDeltaEncoding::get_differences_bytes_with_crc(&patch)
+                    .map(|bytes| bytes.len())
+                    .unwrap_or_default(),
+            );
         }
     }
 
@@ -396,7 +399,7 @@ fn GeneratePatch() -> Html {
                         <div style="width: 100%;">
                             <div>{format!("Total byte size (CRC + differences): {}", current_patch.len())}</div>
                             <div>{format!("Byte size difference only: {}", *current_byte_size)}</div>
-                            <div>{format!("Plain byte size (raw input as UTF-8): {}", (*current_input).as_bytes().len())}</div>
+                            <div>{format!("Plain byte size (raw input as UTF-8): {}", (*current_input).len())}</div>
                             <hr/>
                             <div>{format!("{} difference Tokens:", current_diffs.len())}</div>
                             {
@@ -622,7 +625,7 @@ fn ApplyPatch() -> Html {
                         let mut properties_indexed: Vec<(String, IndexedData)> = Vec::new();
                         if let Ok(serde_json::Value::Object(map)) = a {
                             for (index, (key, value)) in map.iter().enumerate() {
-                                properties_indexed.push((key.to_string(), IndexedData::new(index as u8, value.to_string().trim().as_bytes().to_vec())));
+                                properties_indexed.push((key.to_string(), IndexedData::new(index as Index, value.to_string().trim().as_bytes().to_vec())));
                             }
                         }
                         properties_indexed
@@ -636,7 +639,7 @@ fn ApplyPatch() -> Html {
                 let enc_src = mapped_src.iter().map(|(_, indexed_data)| indexed_data.clone()).collect::<Vec<IndexedData>>();
                 let mut enc = SimpleDirectDeltaEncoding::new(&enc_src);
 
-                let keys = mapped_src.iter().map(|(key, i)| (key.clone(), i.index)).collect::<Vec<(String, u8)>>();
+                let keys = mapped_src.iter().map(|(key, i)| (key.clone(), i.index)).collect::<Vec<(String, Index)>>();
                 if !keys.is_empty() {
                     for (key, index) in keys {
                         enc.change_index_mapping(index, key.as_bytes());
@@ -672,7 +675,7 @@ fn ApplyPatch() -> Html {
                             let mut result = "{ ".to_string();
                             if let Ok(serde_json::Value::Object(map)) = a {
                                 for (index, (key, value)) in map.iter().enumerate() {
-                                    let b = patched.iter().find(|i|i.index == index as u8);
+                                    let b = patched.iter().find(|i|i.index == index as Index);
                                     if let Some(b) = b {
                                         let name = if let Some(n_changed) = &b.map_name_changed {
                                             String::from_utf8(n_changed.to_owned()).expect("Failed to convert patched data to string")